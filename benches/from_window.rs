@@ -0,0 +1,60 @@
+//! Container::from_window lookup cost as the tree grows.
+//!
+//! Needs a display the manager can take over, e.g.
+//!     Xvfb :2 & DISPLAY=:2 cargo bench --bench from_window
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use xcb::x;
+
+use wm::rect::Rect;
+use wm::window::Window;
+use wm::manager::Manager;
+
+const LOOKUPS: usize = 1_000_000;
+const SIZES: &[usize] = &[10, 100, 1_000, 10_000];
+
+fn main() {
+    let mut mgr = match Manager::connect(None, None) {
+        Ok(mgr) => mgr,
+        Err(e) => {
+            eprintln!("skipping from_window benchmark: {}", e);
+            return;
+        }
+    };
+
+    let root = mgr.root.root();
+    let mut windows: Vec<x::Window> = vec![];
+
+    println!("{:>8} {:>12} {:>12}", "windows", "hit (ns)", "miss (ns)");
+
+    for &size in SIZES {
+        /* the windows are never created on the server, we only need ids */
+        while windows.len() < size {
+            let window: x::Window = mgr.conn().raw().generate_id();
+            let win = Window::new(mgr.conn().clone(), window, Rect::new(0, 0, 1, 1), true, true);
+
+            mgr.root.insert(root, win).expect("root is never stale");
+            windows.push(window);
+        }
+
+        let start = Instant::now();
+        for i in 0..LOOKUPS {
+            /* stride through the windows so every lookup lands somewhere new */
+            let window = windows[(i * 7919) % windows.len()];
+            black_box(mgr.root.from_window(black_box(window)));
+        }
+        let hit = start.elapsed().as_nanos() as f64 / LOOKUPS as f64;
+
+        let missing: x::Window = mgr.conn().raw().generate_id();
+
+        let start = Instant::now();
+        for _ in 0..LOOKUPS {
+            black_box(mgr.root.from_window(black_box(missing)));
+        }
+        let miss = start.elapsed().as_nanos() as f64 / LOOKUPS as f64;
+
+        println!("{:>8} {:>12.1} {:>12.1}", size, hit, miss);
+    }
+}
@@ -0,0 +1,165 @@
+use slab::Slab;
+
+/// Handle to a value in an Arena, tagged with the generation of its slot so
+/// a key outliving its value never refers to whatever reuses the slot
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct Key {
+    index: usize,
+    generation: u32,
+}
+
+impl Key {
+    #[inline]
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Number of times the slot was freed before the value was inserted
+    #[inline]
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+}
+
+/// Slab handing out generational keys
+pub struct Arena<T> {
+    slab: Slab<T>,
+    /* generation of every slab index, bumped each time the index is freed */
+    generations: Vec<u32>,
+}
+
+impl<T> Default for Arena<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Arena<T> {
+    pub fn new() -> Self {
+        Arena {
+            slab: Slab::new(),
+            generations: vec![],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.slab.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slab.is_empty()
+    }
+
+    /// Key the next inserted value will get
+    pub fn vacant_key(&self) -> Key {
+        let index = self.slab.vacant_key();
+
+        Key {
+            index,
+            generation: self.generations.get(index).copied().unwrap_or(0),
+        }
+    }
+
+    pub fn insert(&mut self, value: T) -> Key {
+        let index = self.slab.insert(value);
+
+        if index >= self.generations.len() {
+            self.generations.resize(index + 1, 0);
+        }
+
+        self.key(index)
+    }
+
+    /// Remove a value, invalidating its key. Returns None if key is stale
+    pub fn remove(&mut self, key: Key) -> Option<T> {
+        if !self.contains(key) {
+            return None;
+        }
+
+        self.generations[key.index] = self.generations[key.index].wrapping_add(1);
+        Some(self.slab.remove(key.index))
+    }
+
+    /// Check that key still refers to a value in this arena
+    pub fn contains(&self, key: Key) -> bool {
+        self.slab.contains(key.index) && self.generations[key.index] == key.generation
+    }
+
+    pub fn get(&self, key: Key) -> Option<&T> {
+        if self.contains(key) { self.slab.get(key.index) } else { None }
+    }
+
+    pub fn get_mut(&mut self, key: Key) -> Option<&mut T> {
+        if self.contains(key) { self.slab.get_mut(key.index) } else { None }
+    }
+
+    /// Current key of an occupied index. Panics if the index is vacant
+    pub fn key(&self, index: usize) -> Key {
+        assert!(self.slab.contains(index), "vacant arena index {}", index);
+
+        Key {
+            index,
+            generation: self.generations[index],
+        }
+    }
+
+    /// Get a value by index, regardless of its generation
+    pub fn at(&self, index: usize) -> Option<&T> {
+        self.slab.get(index)
+    }
+
+    /// Get a mutable value by index, regardless of its generation
+    pub fn at_mut(&mut self, index: usize) -> Option<&mut T> {
+        self.slab.get_mut(index)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Key, &T)> {
+        self.slab.iter().map(|(i, v)| (Key { index: i, generation: self.generations[i] }, v))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Key, &mut T)> {
+        let generations = &self.generations;
+        self.slab.iter_mut().map(|(i, v)| (Key { index: i, generation: generations[i] }, v))
+    }
+}
+
+impl<T> std::ops::Index<usize> for Arena<T> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.slab[index]
+    }
+}
+
+impl<T> std::ops::IndexMut<usize> for Arena<T> {
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        &mut self.slab[index]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stale() {
+        let mut arena = Arena::new();
+
+        let one = arena.insert(1);
+        assert_eq!(arena.vacant_key().index(), 1);
+        assert_eq!(arena.remove(one), Some(1));
+        assert_eq!(arena.remove(one), None);
+
+        /* the slot is reused with a new generation */
+        assert_eq!(arena.vacant_key().index(), one.index());
+        let two = arena.insert(2);
+
+        assert_eq!(two.index(), one.index());
+        assert_ne!(two, one);
+        assert_eq!(arena.vacant_key().generation(), 0);
+        assert!(!arena.contains(one));
+        assert_eq!(arena.get(one), None);
+        assert_eq!(arena.get(two), Some(&2));
+        assert_eq!(arena.key(two.index()), two);
+    }
+}
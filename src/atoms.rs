@@ -0,0 +1,36 @@
+xcb::atoms_struct! {
+    /// Atoms the manager reads or writes, interned once per connection
+    #[derive(Copy, Clone, Debug)]
+    pub struct Atoms {
+        pub utf8_string => b"UTF8_STRING" only_if_exists = false,
+        pub wm_protocols => b"WM_PROTOCOLS" only_if_exists = false,
+        pub wm_delete_window => b"WM_DELETE_WINDOW" only_if_exists = false,
+        pub wm_take_focus => b"WM_TAKE_FOCUS" only_if_exists = false,
+        pub wm_state => b"WM_STATE" only_if_exists = false,
+        pub wm_change_state => b"WM_CHANGE_STATE" only_if_exists = false,
+        pub net_supported => b"_NET_SUPPORTED" only_if_exists = false,
+        pub net_supporting_wm_check => b"_NET_SUPPORTING_WM_CHECK" only_if_exists = false,
+        pub net_wm_name => b"_NET_WM_NAME" only_if_exists = false,
+        pub net_wm_pid => b"_NET_WM_PID" only_if_exists = false,
+        pub wm_window_role => b"WM_WINDOW_ROLE" only_if_exists = false,
+        pub net_client_list => b"_NET_CLIENT_LIST" only_if_exists = false,
+        pub net_client_list_stacking => b"_NET_CLIENT_LIST_STACKING" only_if_exists = false,
+        pub net_active_window => b"_NET_ACTIVE_WINDOW" only_if_exists = false,
+        pub net_close_window => b"_NET_CLOSE_WINDOW" only_if_exists = false,
+        pub net_wm_state => b"_NET_WM_STATE" only_if_exists = false,
+        pub net_wm_state_fullscreen => b"_NET_WM_STATE_FULLSCREEN" only_if_exists = false,
+        pub net_wm_state_hidden => b"_NET_WM_STATE_HIDDEN" only_if_exists = false,
+        pub net_wm_window_type => b"_NET_WM_WINDOW_TYPE" only_if_exists = false,
+        pub net_wm_strut => b"_NET_WM_STRUT" only_if_exists = false,
+        pub net_wm_strut_partial => b"_NET_WM_STRUT_PARTIAL" only_if_exists = false,
+        pub net_workarea => b"_NET_WORKAREA" only_if_exists = false,
+        pub net_wm_window_type_normal => b"_NET_WM_WINDOW_TYPE_NORMAL" only_if_exists = false,
+        pub net_wm_window_type_dialog => b"_NET_WM_WINDOW_TYPE_DIALOG" only_if_exists = false,
+        pub net_wm_window_type_dock => b"_NET_WM_WINDOW_TYPE_DOCK" only_if_exists = false,
+        pub net_wm_window_type_splash => b"_NET_WM_WINDOW_TYPE_SPLASH" only_if_exists = false,
+        pub net_wm_window_type_utility => b"_NET_WM_WINDOW_TYPE_UTILITY" only_if_exists = false,
+        pub net_wm_window_type_toolbar => b"_NET_WM_WINDOW_TYPE_TOOLBAR" only_if_exists = false,
+        pub net_wm_window_type_notification => b"_NET_WM_WINDOW_TYPE_NOTIFICATION" only_if_exists = false,
+        pub net_wm_window_type_desktop => b"_NET_WM_WINDOW_TYPE_DESKTOP" only_if_exists = false,
+    }
+}
@@ -1,10 +1,15 @@
-use xcb::x;
+use std::collections::HashMap;
+
+use xcb::{x, Xid};
 
 use crate::layout;
+use crate::arena::Key;
 use crate::tree::Tree;
-use crate::rect::Rect;
+use crate::rect::{Rect, Direction};
 use crate::error::Error;
-use crate::window::Window;
+use crate::window::{Window, WindowType, WmState};
+use crate::ewmh::{Ewmh, ActivateSource, Strut};
+use crate::workspace::{Workspaces, WorkspaceId};
 use crate::manager::{Connection, Event};
 
 
@@ -19,20 +24,45 @@ fn get_window_rect(conn: &xcb::Connection, window: x::Window) -> Result<Rect, Er
 }
 
 
-#[repr(transparent)]
 pub struct Layout {
-    inner: Box<dyn layout::Layout>
+    inner: Box<dyn layout::Layout>,
+    /* the scope this layout was last arranged in, if any */
+    scope: Option<Rect>,
+    /* children by tree key, most recently focused last */
+    history: Vec<Key>,
+    weight: f32,
 }
 
 impl Layout {
     #[inline]
     pub fn new<L: layout::Layout + 'static>(layout: L) -> Self {
         Self {
-            inner: Box::new(layout)
+            inner: Box::new(layout),
+            scope: None,
+            history: vec![],
+            weight: 1.0,
         }
     }
+
+    #[inline]
+    pub fn scope(&self) -> Option<Rect> {
+        self.scope
+    }
+
+    /// Relative share of the parent layout's scope
+    #[inline]
+    pub fn weight(&self) -> f32 {
+        self.weight
+    }
+
+    #[inline]
+    pub fn set_weight(&mut self, weight: f32) {
+        self.weight = weight;
+    }
 }
 
+/* windows far outnumber layouts, boxing them would only add indirection */
+#[allow(clippy::large_enum_variant)]
 pub enum ContainerNode {
     Window(Window),
     Layout(Layout),
@@ -96,6 +126,251 @@ impl ContainerNode {
     }
 }
 
+/// Tree values that layouts can arrange. This lets an arrangement be
+/// planned without talking to the X server
+pub(crate) trait Arrange {
+    fn layout(&self) -> Option<&Layout>;
+    fn layout_mut(&mut self) -> Option<&mut Layout>;
+    /// Describe this node to its parent layout
+    fn describe(&self) -> layout::Child;
+
+    /// Whether the node takes part in its parent's arrangement at all
+    fn arranged(&self) -> bool {
+        true
+    }
+
+    /// Whether the node may take the focus
+    fn selectable(&self) -> bool {
+        true
+    }
+}
+
+impl Arrange for ContainerNode {
+    fn layout(&self) -> Option<&Layout> {
+        match self {
+            ContainerNode::Layout(layout) => Some(layout),
+            ContainerNode::Window(_) => None,
+        }
+    }
+
+    fn layout_mut(&mut self) -> Option<&mut Layout> {
+        match self {
+            ContainerNode::Layout(layout) => Some(layout),
+            ContainerNode::Window(_) => None,
+        }
+    }
+
+    fn describe(&self) -> layout::Child {
+        match self {
+            ContainerNode::Window(window) => {
+                let mut child = layout::Child::new(window.size());
+                child.weight = window.weight();
+                child.floating = window.floating();
+                child.min = window.size_hints().min;
+                child.max = window.size_hints().max;
+                child.class = window.class().map(str::to_owned);
+                child
+            }
+            ContainerNode::Layout(layout) => layout.describe(),
+        }
+    }
+
+    fn arranged(&self) -> bool {
+        match self {
+            ContainerNode::Window(window) => !window.minimized(),
+            ContainerNode::Layout(_) => true,
+        }
+    }
+
+    fn selectable(&self) -> bool {
+        match self {
+            ContainerNode::Window(window) => window.selectable(),
+            ContainerNode::Layout(_) => true,
+        }
+    }
+}
+
+impl Layout {
+    fn describe(&self) -> layout::Child {
+        let mut child = layout::Child::new(self.scope.unwrap_or(Rect::new(0, 0, 0, 0)));
+        child.weight = self.weight;
+        child
+    }
+}
+
+/// Children of the layout at index that are still in the tree, most
+/// recently focused first
+fn history<'a, N: Arrange>(tree: &'a Tree<N>, index: usize) -> impl Iterator<Item = usize> + 'a {
+    let history = match tree[index].value.layout() {
+        Some(layout) => &layout.history[..],
+        None => &[],
+    };
+
+    history.iter().rev()
+        .filter(|child| tree.contains(**child))
+        .map(|child| child.index())
+        .filter(move |child| tree[*child].parent() == Some(index) && tree[*child].value.arranged())
+}
+
+/// New value of a _NET_WM_STATE state after a client asked to remove (0),
+/// add (1) or toggle (2) it
+fn state_action(action: u32, current: bool) -> bool {
+    match action {
+        0 => false,
+        1 => true,
+        2 => !current,
+        _ => current,
+    }
+}
+
+/// Focused child of the layout at index. Falls back to the last child when
+/// the layout has no focus yet. Children left out of arrangement are skipped
+fn focused_child<N: Arrange>(tree: &Tree<N>, index: usize) -> Option<usize> {
+    tree[index].value.layout()?;
+    history(tree, index).next().or_else(|| tree.children(index).rfind(|i| tree[*i].value.arranged()))
+}
+
+/// Node the focus path below index ends on
+fn focus_leaf<N: Arrange>(tree: &Tree<N>, mut index: usize) -> usize {
+    while let Some(child) = focused_child(tree, index) {
+        index = child;
+    }
+
+    index
+}
+
+/// Check that every layout above index has it on its focus path
+fn on_focus_path<N: Arrange>(tree: &Tree<N>, mut index: usize) -> bool {
+    while let Some(parent) = tree[index].parent() {
+        if tree[parent].value.layout().is_none() {
+            break;
+        }
+
+        if focused_child(tree, parent) != Some(index) {
+            return false;
+        }
+
+        index = parent;
+    }
+
+    true
+}
+
+/// Make index the focused child of its parent, and so on up the tree
+fn set_focus_path<N: Arrange>(tree: &mut Tree<N>, mut index: usize) {
+    while let Some(parent) = tree[index].parent() {
+        if tree[parent].value.layout().is_none() {
+            break;
+        }
+
+        /* rebuild the history, dropping removed children */
+        let mut children: Vec<_> = history(tree, parent).filter(|i| *i != index).collect();
+        children.reverse();
+        children.push(index);
+
+        let entries = children.into_iter().map(|i| tree.key(i)).collect();
+
+        if let Some(layout) = tree[parent].value.layout_mut() {
+            layout.history = entries;
+        }
+
+        index = parent;
+    }
+}
+
+/// Previous focus of the nearest ancestor layout that has one, excluding
+/// the path to index
+fn last_focus<N: Arrange>(tree: &Tree<N>, mut index: usize) -> Option<usize> {
+    while let Some(parent) = tree[index].parent() {
+        tree[parent].value.layout()?;
+
+        if let Some(child) = history(tree, parent).find(|i| *i != index) {
+            return Some(child);
+        }
+
+        index = parent;
+    }
+
+    None
+}
+
+/// Next or previous sibling of index that can take the focus, wrapping
+/// around. Minimized and unselectable siblings are skipped
+fn sibling<N: Arrange>(tree: &Tree<N>, index: usize, forward: bool) -> Option<usize> {
+    let parent = tree[index].parent()?;
+    let siblings: Vec<_> = tree.children(parent).collect();
+
+    let count = siblings.len();
+    let position = siblings.iter().position(|i| *i == index)?;
+
+    (1..count)
+        .map(|step| if forward {
+            siblings[(position + step) % count]
+        } else {
+            siblings[(position + count - step) % count]
+        })
+        .find(|i| tree[*i].value.arranged() && tree[*i].value.selectable())
+}
+
+/// Plan the arrangement of the sub-tree at index, collecting the cell of
+/// every window it contains. Focus is only handed out when focus is set,
+/// i.e. index is on the focus path.
+fn plan<N: Arrange>(tree: &mut Tree<N>, index: usize, scope: Rect, focus: bool, cells: &mut Vec<(usize, layout::Cell)>) {
+    /* children must first be collected. layouts need to know
+     * about every window they are dealing with. minimized ones are
+     * not dealt with at all */
+    let children: Vec<usize> = tree.children(index)
+        .filter(|i| tree[*i].value.arranged())
+        .collect();
+    let focused = focused_child(tree, index);
+
+    let descriptors: Vec<_> = children.iter().map(|i| {
+        let mut child = tree[*i].value.describe();
+        child.focused = Some(*i) == focused;
+        child
+    }).collect();
+
+    let layout = match tree[index].value.layout_mut() {
+        Some(layout) => layout,
+        None => return,
+    };
+
+    layout.scope = Some(scope);
+
+    let mut arranged = layout.inner.arrange_all(&descriptors, scope);
+
+    /* children a layout forgot about are hidden */
+    arranged.resize_with(children.len(), || layout::Cell::Hide);
+
+    for (child, mut cell) in std::iter::zip(children, arranged) {
+        /* off the focus path, the focused child is merely shown */
+        if let (false, layout::Cell::Focus(size)) = (focus, &cell) {
+            cell = layout::Cell::Show(*size);
+        }
+
+        if tree[child].value.layout().is_none() {
+            cells.push((child, cell));
+            continue;
+        }
+
+        match cell {
+            layout::Cell::Hide => {
+                let leaves = tree.iter_at(child)
+                    .filter(|i| tree[*i].value.layout().is_none())
+                    .map(|i| (i, layout::Cell::Hide));
+
+                cells.extend(leaves);
+            }
+            layout::Cell::Show(size) => {
+                plan(tree, child, size, false, cells);
+            }
+            layout::Cell::Focus(size) => {
+                plan(tree, child, size, true, cells);
+            }
+        }
+    }
+}
+
 impl From<Window> for ContainerNode {
     fn from(window: Window) -> Self {
         ContainerNode::Window(window)
@@ -108,15 +383,31 @@ impl<L: layout::Layout + 'static> From<L> for ContainerNode {
     }
 }
 
-#[repr(transparent)]
+/// Handle to a node in a Container
 #[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
-pub struct ContainerId {
-    id: usize,
+pub struct ContainerId(Key);
+
+impl ContainerId {
+    /// Tree index of the node
+    #[inline]
+    fn index(&self) -> usize {
+        self.0.index()
+    }
 }
 
 pub struct Container {
     conn: Connection,
     tree: Tree<ContainerNode>,
+    /* every window node in the tree, by X window */
+    windows: HashMap<x::Window, ContainerId>,
+    /* the focused container. may be a layout */
+    focused: Option<ContainerId>,
+    workspaces: Workspaces,
+    ewmh: Ewmh,
+    /* minimized windows, most recently minimized last */
+    minimized: Vec<ContainerId>,
+    /* transient windows, by the window they are a transient of */
+    transients: HashMap<ContainerId, Vec<ContainerId>>,
 }
 
 impl Container {
@@ -125,192 +416,1678 @@ impl Container {
         let size = get_window_rect(conn.raw(), conn.root())?;
         let root = Window::new(conn.clone(), conn.root(), size, false, false);
         let node = ContainerNode::Window(root);
+        let ewmh = Ewmh::new(conn.clone())?;
 
-        Ok(Self {
-            conn: conn,
+        let mut container = Self {
+            conn,
             tree: Tree::new(node),
-        })
+            windows: HashMap::new(),
+            focused: None,
+            workspaces: Workspaces::new(),
+            ewmh,
+            minimized: vec![],
+            transients: HashMap::new(),
+        };
+
+        container.index(container.tree.root());
+
+        Ok(container)
     }
 
-    pub fn insert<T: Into<ContainerNode>>(&mut self, parent: ContainerId, value: T) -> ContainerId {
-        ContainerId {
-            id: self.tree.insert(parent.id, value.into())
+    /// Add the windows of the sub-tree at index to the window lookup
+    fn index(&mut self, index: usize) {
+        let indices: Vec<_> = self.tree.iter_at(index).collect();
+
+        for i in indices.into_iter() {
+            if let ContainerNode::Window(w) = &self.tree[i].value {
+                let id = self.id(i);
+                self.windows.insert(w.window(), id);
+            }
         }
     }
 
-    pub fn from_window(&self, window: x::Window) -> Option<ContainerId> {
-        let windows = self.tree.iter().filter_map(|(id, win)| {
-            match &win.value {
-                ContainerNode::Window(x) => Some((id, x.window())),
-                ContainerNode::Layout(_) => None,
+    /// Remove the windows of the sub-tree at index from the window lookup
+    fn unindex(&mut self, index: usize) {
+        for i in self.tree.iter_at(index) {
+            if let ContainerNode::Window(w) = &self.tree[i].value {
+                self.windows.remove(&w.window());
             }
-        });
+        }
+    }
+
+    /// Current id of the node at a tree index
+    fn id(&self, index: usize) -> ContainerId {
+        ContainerId(self.tree.key(index))
+    }
+
+    /// The given event time, or the current server time without one
+    pub(crate) fn time(&self, time: Option<x::Timestamp>) -> Result<x::Timestamp, Error> {
+        match time {
+            Some(time) => Ok(time),
+            None => self.ewmh.time(),
+        }
+    }
+
+    /// Workspace containing the node at index, if any
+    fn workspace_at(&self, index: usize) -> Option<WorkspaceId> {
+        std::iter::once(index)
+            .chain(self.tree.ancestors(index))
+            .find_map(|i| self.workspaces.by_layout(self.id(i)))
+    }
+
+    /// Check whether the node at index is on a workspace that is not shown
+    fn hidden(&self, index: usize) -> bool {
+        self.workspace_at(index)
+            .and_then(|ws| self.workspaces.get(ws))
+            .is_some_and(|ws| !ws.visible())
+    }
+
+    /// Window node the window at index is a transient of
+    fn transient_parent(&self, index: usize) -> Option<usize> {
+        let parent = match &self.tree[index].value {
+            ContainerNode::Window(window) => window.transient_for()?,
+            ContainerNode::Layout(_) => return None,
+        };
+
+        match self.tree.get(parent.index()) {
+            Some(node) if self.contains(parent) && matches!(node.value, ContainerNode::Window(_)) => Some(parent.index()),
+            _ => None,
+        }
+    }
 
-        for (id, x) in windows {
-            if window == x {
-                return Some(ContainerId { id })
+    /// Windows that are transients of a window in the sub-tree at index,
+    /// but not inside it themselves
+    fn transients(&self, index: usize) -> Vec<usize> {
+        self.tree.iter_at(index)
+            .filter_map(|i| self.transients.get(&self.id(i)))
+            .flatten()
+            .filter(|t| self.contains(**t) && !self.tree.is_ancestor(index, t.index()))
+            .map(|t| t.index())
+            .collect()
+    }
+
+    /// Size of the monitor the node at index is shown on. Nodes outside of
+    /// workspaces span the root window
+    fn screen(&self, index: usize) -> Rect {
+        let screen = self.workspace_at(index)
+            .and_then(|ws| self.workspaces.get(ws))
+            .and_then(|ws| ws.screen());
+
+        match screen {
+            Some(screen) => screen,
+            None => self.tree[self.tree.root()].value.as_window_ref().size(),
+        }
+    }
+
+    /// Record the workspace of every window in the sub-tree at index
+    fn tag(&mut self, index: usize) {
+        let workspace = self.workspace_at(index);
+        let indices: Vec<_> = self.tree.iter_at(index).collect();
+
+        for i in indices.into_iter() {
+            if let ContainerNode::Window(w) = &mut self.tree[i].value {
+                w.set_workspace(workspace);
             }
         }
+    }
+
+    /// Id of the root container
+    pub fn root(&self) -> ContainerId {
+        self.id(self.tree.root())
+    }
+
+    /// Check that id still refers to a node in this container
+    pub fn contains(&self, id: ContainerId) -> bool {
+        self.tree.contains(id.0)
+    }
+
+    pub fn get(&self, id: ContainerId) -> Option<&ContainerNode> {
+        if self.contains(id) { Some(&self.tree[id.index()].value) } else { None }
+    }
+
+    pub fn get_mut(&mut self, id: ContainerId) -> Option<&mut ContainerNode> {
+        if self.contains(id) { Some(&mut self.tree[id.index()].value) } else { None }
+    }
+
+    /// Insert value under parent. Returns None if parent is stale
+    pub fn insert<T: Into<ContainerNode>>(&mut self, parent: ContainerId, value: T) -> Option<ContainerId> {
+        if !self.contains(parent) {
+            return None;
+        }
+
+        let index = self.tree.insert(parent.index(), value.into());
+        self.index(index);
+        self.tag(index);
+
+        Some(self.id(index))
+    }
+
+    /// Detach the sub-tree at id, returning it as a new tree. Returns None
+    /// if id is stale or the root
+    pub fn detach(&mut self, id: ContainerId) -> Option<Tree<ContainerNode>> {
+        if !self.contains(id) || id.index() == self.tree.root() {
+            return None;
+        }
+
+        self.unindex(id.index());
+        Some(self.tree.remove(id.index()))
+    }
+
+    /// Graft a tree previously returned by detach() under parent, returning
+    /// the new id of its root. Returns None if parent is stale
+    pub fn attach(&mut self, parent: ContainerId, mut tree: Tree<ContainerNode>) -> Option<ContainerId> {
+        if !self.contains(parent) {
+            return None;
+        }
+
+        let root = tree.root();
+        let index = self.tree.graft(&mut tree, root, parent.index());
+        self.index(index);
+        self.tag(index);
+
+        Some(self.id(index))
+    }
+
+    pub fn from_window(&self, window: x::Window) -> Option<ContainerId> {
+        self.windows.get(&window).copied()
+    }
 
-        None
+    /// Check whether index is on the focus path, i.e. every layout above it
+    /// has it, or one of its ancestors, as their focused child
+    fn on_focus_path(&self, index: usize) -> bool {
+        /* other shown workspaces never take the focus while arranging */
+        match self.workspace_at(index) {
+            Some(ws) if Some(ws) != self.workspaces.current() => false,
+            _ => on_focus_path(&self.tree, index),
+        }
     }
 
-    /// Same as arrange, but using index as the root
+    /// Same as arrange, but using index as the root. Does nothing on
+    /// workspaces that are not shown
     pub fn arrange_at(&mut self, index: usize, size: Rect) -> Result<(), Error> {
-        /* children indices must first be collected. layouts need to know
-         * how many total windows they are dealing with. */
-        let children: Vec<usize> = self.tree.children(index).collect();
-        let count = children.len();
+        self.arrange_with(index, size, None)
+    }
+
+    /// Arrange at index, focusing with the time of the event that caused
+    /// it if there is one
+    fn arrange_with(&mut self, index: usize, size: Rect, time: Option<x::Timestamp>) -> Result<(), Error> {
+        if self.hidden(index) {
+            return Ok(());
+        }
+
+        let focus = self.on_focus_path(index);
+
+        let mut cells = vec![];
+        plan(&mut self.tree, index, size, focus, &mut cells);
 
-        let mut cells = Vec::with_capacity(count);
+        let mut focused = None;
 
-        let is_layout;
-        let node = &mut self.tree[index];
+        /* fullscreen windows cover the monitor instead of their cell */
+        let screen = self.screen(index);
 
-        match &mut node.value {
-            ContainerNode::Layout(layout) => {
-                is_layout = true;
+        /* transients follow their parent, so they go after every other window */
+        cells.sort_by_key(|(leaf, _)| self.transient_parent(*leaf).is_some());
 
-                for i in 0..count {
-                    let cell = layout.inner.arrange(i, count, false, size);
-                    cells.push(cell);
+        for (leaf, cell) in cells {
+            let parent = self.transient_parent(leaf);
+
+            /* a transient is hidden with its parent, and kept above it */
+            let cell = match parent {
+                Some(parent) if !self.tree[parent].value.as_window_ref().visible() => layout::Cell::Hide,
+                _ => cell,
+            };
+
+            let window = match &mut self.tree[leaf].value {
+                ContainerNode::Window(window) => window,
+                ContainerNode::Layout(_) => continue,
+            };
+
+            match cell {
+                layout::Cell::Hide => {
+                    window.hide()?;
                 }
-            }
-            _ => {
-                is_layout = false;
-            }
-        }
-
-        if is_layout {
-            for (child, cell) in std::iter::zip(children.iter(), cells.into_iter()) {
-                let node = &mut self.tree[*child];
-
-                match &mut node.value {
-                    ContainerNode::Window(window) => {
-                        match cell {
-                            layout::Cell::Hide => {
-                                window.hide()?;
-                            }
-                            layout::Cell::Show(size) => {
-                                window.resize(size)?;
-                                window.show()?;
-                            }
-                            layout::Cell::Focus(size) => {
-                                window.resize(size)?;
-                                window.focus()?;
-                                window.show()?;
-                            }
-                        }
-                    }
-                    ContainerNode::Layout(_) => {
-                        match cell {
-                            layout::Cell::Hide => {
-                                self.hide(ContainerId { id: *child })?;
-                            }
-                            layout::Cell::Show(size) => {
-                                self.arrange_at(*child, size)?;
-                            }
-                            layout::Cell::Focus(_) => {
-                                unimplemented!();
-                            }
-                        }
-                    }
+                layout::Cell::Show(size) => {
+                    window.place(size, screen)?;
+                    window.show()?;
+                }
+                layout::Cell::Focus(size) => {
+                    window.place(size, screen)?;
+                    window.show()?;
+                    focused = Some(leaf);
                 }
             }
-        }
 
-        Ok(())
-    }
+            if parent.is_some() && window.visible() {
+                self.raise_at(leaf)?;
+            }
+        }
 
-    /// Arrange this container by resizing all sub-windows according to their parent layouts
-    pub fn arrange(&mut self, scope: Rect) -> Result<(), Error> {
-        self.arrange_at(self.tree.root(), scope)
-    }
+        /* windows must be mapped before they can take focus */
+        if let Some(leaf) = focused {
+            let time = self.time(time)?;
+            self.tree[leaf].value.as_window_ref().focus(time)?;
 
-    pub fn show(&mut self, id: ContainerId) -> Result<(), Error> {
-        let indices: Vec<_> = self.tree.iter_at(id.id).collect();
+            /* the layouts may have picked a window we did not know about */
+            let current = self.focused().map(|id| focus_leaf(&self.tree, id.index()));
 
-        for i in indices.into_iter() {
-            match &mut self.tree[i].value {
-                ContainerNode::Window(w) => { w.show()?; }
-                ContainerNode::Layout(_) => {},
+            if current != Some(leaf) {
+                self.track(Some(self.id(leaf)))?;
             }
         }
 
         Ok(())
     }
 
-    pub fn hide(&mut self, id: ContainerId) -> Result<(), Error> {
-        let indices: Vec<_> = self.tree.iter_at(id.id).collect();
+    /// Outermost arranged layout above index, and its scope
+    fn outer(&self, mut index: usize) -> Option<(usize, Rect)> {
+        let mut outer = None;
 
-        for i in indices.into_iter() {
-            match &mut self.tree[i].value {
-                ContainerNode::Window(w) => { w.hide()?; }
-                ContainerNode::Layout(_) => {},
+        while let Some(parent) = self.tree[index].parent() {
+            match &self.tree[parent].value {
+                ContainerNode::Layout(layout) => {
+                    if let Some(scope) = layout.scope {
+                        outer = Some((parent, scope));
+                    }
+                }
+                ContainerNode::Window(_) => break,
             }
+
+            index = parent;
         }
 
-        Ok(())
+        outer
     }
 
-    pub fn create(&mut self, event: &x::CreateNotifyEvent) {
-        let parent = self.from_window(event.parent())
-            .unwrap_or(ContainerId { id: self.tree.root() });
+    /// Re-arrange the outermost arranged layout at or above index
+    fn rearrange_outer(&mut self, index: usize) -> Result<(), Error> {
+        let own = match &self.tree[index].value {
+            ContainerNode::Layout(layout) => layout.scope.map(|scope| (index, scope)),
+            ContainerNode::Window(_) => None,
+        };
 
-        let size = Rect::new(event.x(), event.y(), event.width(), event.height());
-        let win = Window::new(self.conn.clone(), event.window(), size, !event.override_redirect(), true);
-        let id = self.insert(parent, win);
+        match self.outer(index).or(own) {
+            Some((index, scope)) => self.arrange_at(index, scope),
+            None => Ok(()),
+        }
+    }
 
-        self.conn.produce(Event::WindowCreate {
-            window: id,
-            x: event.x(),
-            y: event.y(),
-            width: event.width(),
-            height: event.height(),
-        });
+    /// Move a container to position under parent, keeping its id and those
+    /// of everything inside it. Does nothing if either id is stale, or
+    /// parent is inside the container
+    pub fn move_to(&mut self, id: ContainerId, parent: ContainerId, position: usize) -> Result<(), Error> {
+        if !self.contains(id) || !self.contains(parent) || self.tree.is_ancestor(id.index(), parent.index()) {
+            return Ok(());
+        }
+
+        let old = self.tree[id.index()].parent().expect("root is an ancestor of every parent");
+
+        self.tree.move_to(id.index(), parent.index(), position);
+        self.tag(id.index());
+
+        self.rearrange_outer(old)?;
+        self.rearrange_outer(parent.index())?;
+
+        /* the focus path may run through the moved container */
+        match self.focused() {
+            Some(focused) if self.tree.is_ancestor(id.index(), focused.index()) => self.focus(focused, None),
+            _ => Ok(()),
+        }
     }
 
-    pub fn configure(&mut self, event: &x::ConfigureRequestEvent) {
-        match self.from_window(event.window()) {
-            Some(id) => {
-                self.conn.produce(Event::WindowResize {
-                    window: id,
-                    x: event.x(),
-                    y: event.y(),
-                    width: event.width(),
-                    height: event.height(),
-                });
-            },
-            None => {
-                eprintln!("Unknown window configured!");
+    /// Swap two containers in place, keeping their ids. Does nothing if either
+    /// id is stale, or one contains the other
+    pub fn swap(&mut self, a: ContainerId, b: ContainerId) -> Result<(), Error> {
+        if !self.contains(a) || !self.contains(b) {
+            return Ok(());
+        }
+
+        if self.tree.is_ancestor(a.index(), b.index()) || self.tree.is_ancestor(b.index(), a.index()) {
+            return Ok(());
+        }
+
+        self.tree.swap(a.index(), b.index());
+        self.tag(a.index());
+        self.tag(b.index());
+
+        self.rearrange_outer(a.index())?;
+        self.rearrange_outer(b.index())?;
+
+        match self.focused() {
+            Some(focused) if self.tree.is_ancestor(a.index(), focused.index()) || self.tree.is_ancestor(b.index(), focused.index()) => {
+                self.focus(focused, None)
             }
+            _ => Ok(()),
         }
     }
 
-    pub fn map(&mut self, event: &x::MapRequestEvent) {
-        match self.from_window(event.window()) {
-            Some(id) => {
-                self.conn.produce(Event::WindowShow {
-                    window: id
+    /// Rotate the children of a container n places towards the end
+    pub fn rotate(&mut self, id: ContainerId, n: isize) -> Result<(), Error> {
+        if !self.contains(id) {
+            return Ok(());
+        }
+
+        self.tree.rotate_children(id.index(), n);
+        self.rearrange_outer(id.index())
+    }
+
+    /// The focused container, if any
+    pub fn focused(&self) -> Option<ContainerId> {
+        self.focused.filter(|id| self.contains(*id))
+    }
+
+    /// Record a new focus, announcing it with a FocusChange event and
+    /// publishing the window it leads to as _NET_ACTIVE_WINDOW
+    fn track(&mut self, new: Option<ContainerId>) -> Result<(), Error> {
+        let old = self.focused();
+
+        if old != new {
+            self.focused = new;
+            self.conn.produce(Event::FocusChange {
+                old,
+                new,
+            });
+
+            let root = self.tree.root();
+            let active = new
+                .map(|id| focus_leaf(&self.tree, id.index()))
+                .and_then(|leaf| match &self.tree[leaf].value {
+                    ContainerNode::Window(w) if leaf != root => Some(w.window()),
+                    _ => None,
                 });
-            },
-            None => {
-                eprintln!("Unknown window mapped!");
-            }
+
+            self.ewmh.set_active(active)?;
         }
+
+        Ok(())
     }
-}
 
-impl std::ops::Index<ContainerId> for Container {
-    type Output = ContainerNode;
+    /// Make id the focused container and the focused child of every layout
+    /// above it, then re-arrange the outermost of them. If id is a layout,
+    /// input focus ends on the window its own focus path leads to. On a
+    /// workspace that is not shown, id is only remembered as its focus.
+    /// time is that of the event asking for the focus, if there is one.
+    pub fn focus(&mut self, id: ContainerId, time: Option<x::Timestamp>) -> Result<(), Error> {
+        if !self.contains(id) {
+            return Ok(());
+        }
+
+        /* docks and the like never take the focus, nor do minimized windows
+         * until restored */
+        match &self.tree[id.index()].value {
+            ContainerNode::Window(w) if !w.selectable() || w.minimized() => return Ok(()),
+            _ => {}
+        }
+
+        set_focus_path(&mut self.tree, id.index());
 
-    fn index(&self, id: ContainerId) -> &Self::Output {
-        &self.tree[id.id].value
+        if self.hidden(id.index()) {
+            return Ok(());
+        }
+        self.track(Some(id))?;
+
+        /* the outermost arranged layout decides what becomes visible */
+        match self.outer(id.index()) {
+            Some((index, scope)) => self.arrange_with(index, scope, time),
+            None => match &self.tree[id.index()].value {
+                ContainerNode::Window(window) => window.focus(self.time(time)?),
+                ContainerNode::Layout(_) => Ok(()),
+            }
+        }
+    }
+
+    /// Focus the next sibling of the focused container, wrapping around
+    pub fn focus_next(&mut self) -> Result<(), Error> {
+        self.focus_sibling(true)
+    }
+
+    /// Focus the previous sibling of the focused container, wrapping around
+    pub fn focus_prev(&mut self) -> Result<(), Error> {
+        self.focus_sibling(false)
+    }
+
+    fn focus_sibling(&mut self, forward: bool) -> Result<(), Error> {
+        let next = self.focused().and_then(|id| sibling(&self.tree, id.index(), forward));
+
+        match next {
+            Some(index) => self.focus(self.id(index), None),
+            None => Ok(()),
+        }
+    }
+
+    /// Focus the layout containing the focused container
+    pub fn focus_parent(&mut self) -> Result<(), Error> {
+        let parent = self.focused().and_then(|id| self.tree[id.index()].parent());
+
+        match parent {
+            Some(index) if self.tree[index].value.layout().is_some() => {
+                self.focus(self.id(index), None)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Focus the focused child of the focused layout
+    pub fn focus_child(&mut self) -> Result<(), Error> {
+        let child = self.focused().and_then(|id| focused_child(&self.tree, id.index()));
+
+        match child {
+            Some(index) => self.focus(self.id(index), None),
+            None => Ok(()),
+        }
+    }
+
+    /// Return to the previously focused container, searching the focus
+    /// history of each layout from the focused container outwards
+    pub fn focus_last(&mut self) -> Result<(), Error> {
+        let last = self.focused().and_then(|id| last_focus(&self.tree, id.index()));
+
+        match last {
+            Some(index) => self.focus(self.id(index), None),
+            None => Ok(()),
+        }
+    }
+
+    /// Geometry of the node at index: a window's size, or the scope a layout
+    /// was last arranged in
+    fn geometry(&self, index: usize) -> Option<Rect> {
+        match &self.tree[index].value {
+            ContainerNode::Window(window) => Some(window.size()),
+            ContainerNode::Layout(layout) => layout.scope,
+        }
+    }
+
+    /// Nearest visible window in a direction from the node at index, ignoring
+    /// the windows inside it
+    fn neighbour(&self, index: usize, direction: Direction) -> Option<usize> {
+        let from = self.geometry(index)?;
+        let inside: Vec<_> = self.tree.iter_at(index).collect();
+
+        let candidates = self.tree.iter().filter_map(|(i, node)| {
+            match &node.value {
+                ContainerNode::Window(w) if w.visible() && w.managed() && w.selectable() && !inside.contains(&i) => {
+                    Some((i, w.size()))
+                }
+                _ => None,
+            }
+        });
+
+        from.nearest(direction, candidates)
+    }
+
+    /// Focus the nearest visible window in a direction from the focused
+    /// container. Works across layouts and monitors, as windows are compared
+    /// by their position on the screen
+    pub fn focus_direction(&mut self, direction: Direction) -> Result<(), Error> {
+        let next = self.focused().and_then(|id| self.neighbour(id.index(), direction));
+
+        match next {
+            Some(index) => self.focus(self.id(index), None),
+            None => Ok(()),
+        }
+    }
+
+    /// Swap the focused container with the nearest visible window in a
+    /// direction, keeping it focused
+    pub fn move_direction(&mut self, direction: Direction) -> Result<(), Error> {
+        let focused = match self.focused() {
+            Some(id) if id.index() != self.tree.root() => id,
+            _ => return Ok(()),
+        };
+
+        match self.neighbour(focused.index(), direction) {
+            Some(index) => self.swap(focused, self.id(index)),
+            None => Ok(()),
+        }
+    }
+
+    /// A client moved input focus itself. Update the model without fighting it
+    pub fn focus_in(&mut self, event: &x::FocusInEvent) -> Result<(), Error> {
+        match (event.mode(), event.detail()) {
+            (x::NotifyMode::Grab, _) | (x::NotifyMode::Ungrab, _) => return Ok(()),
+            (_, x::NotifyDetail::Pointer) | (_, x::NotifyDetail::PointerRoot) | (_, x::NotifyDetail::None) => return Ok(()),
+            _ => {}
+        }
+
+        let id = match self.from_window(event.event()) {
+            Some(id) if id.index() != self.tree.root() => id,
+            _ => return Ok(()),
+        };
+
+        if !self.tree[id.index()].value.as_window_ref().selectable() {
+            return Ok(());
+        }
+
+        /* focusing a layout also gives its window input focus */
+        if self.focused().map(|f| focus_leaf(&self.tree, f.index())) != Some(id.index()) {
+            set_focus_path(&mut self.tree, id.index());
+            self.track(Some(id))?;
+        }
+
+        Ok(())
+    }
+
+    /// Apply the default policy for the type of the window at index: docks,
+    /// desktops and notifications stay out of layouts and never take the
+    /// focus, dialogs and splash screens float in the middle of the screen
+    fn apply_type(&mut self, index: usize) -> Result<(), Error> {
+        let screen = self.screen(index);
+
+        let window = match &mut self.tree[index].value {
+            ContainerNode::Window(window) => window,
+            ContainerNode::Layout(_) => return Ok(()),
+        };
+
+        let kind = window.window_type();
+        window.set_selectable(kind.tiled());
+
+        match kind {
+            WindowType::Dialog | WindowType::Splash => {
+                window.set_floating(true);
+                window.resize(window.size().centered(&screen))?;
+                self.raise_at(index)?;
+            }
+            WindowType::Desktop => {
+                self.lower_at(index)?;
+            }
+            _ => {}
+        }
+
+        /* the type may change after the window joined a layout */
+        let root = self.tree.root();
+        let parent = self.tree[index].parent();
+
+        if let (false, Some(parent)) = (kind.tiled(), parent) {
+            if parent != root {
+                self.tree.move_to(index, root, usize::MAX);
+                self.tag(index);
+                self.rearrange_outer(parent)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Float a transient window centered over the window it belongs to,
+    /// next to it in the same layout
+    fn apply_transient(&mut self, index: usize) -> Result<(), Error> {
+        let parent = match self.tree[index].value.as_window_ref().load_transient_for()? {
+            Some(window) => self.from_window(window),
+            None => None,
+        };
+
+        let parent = match parent {
+            Some(parent) if parent.index() != index && parent != self.root() => parent,
+            _ => return Ok(()),
+        };
+
+        let id = self.id(index);
+        let window = self.tree[index].value.as_window_mut();
+
+        if let Some(old) = window.transient_for() {
+            if let Some(transients) = self.transients.get_mut(&old) {
+                transients.retain(|t| *t != id);
+            }
+        }
+
+        window.set_transient_for(Some(parent));
+        self.transients.entry(parent).or_default().push(id);
+
+        /* the parent may still be a detached or unmapped top-level window */
+        let over = match &self.tree[parent.index()].value {
+            ContainerNode::Window(w) if w.visible() => w.size(),
+            _ => self.screen(index),
+        };
+
+        let window = self.tree[index].value.as_window_mut();
+        window.set_floating(true);
+        window.resize(window.size().centered(&over))?;
+
+        if let Some(layout) = self.tree[parent.index()].parent() {
+            let position = self.tree.sibling_position(parent.index()) + 1;
+            self.tree.move_to(index, layout, position);
+            self.tag(index);
+        }
+
+        self.raise_at(index)
+    }
+
+    /// Stack a window above its siblings, keeping _NET_CLIENT_LIST_STACKING
+    /// in step. Does nothing for layouts
+    pub fn raise(&mut self, id: ContainerId) -> Result<(), Error> {
+        match self.get(id) {
+            Some(ContainerNode::Window(_)) if id != self.root() => self.raise_at(id.index()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Stack a window below its siblings, keeping _NET_CLIENT_LIST_STACKING
+    /// in step. Does nothing for layouts
+    pub fn lower(&mut self, id: ContainerId) -> Result<(), Error> {
+        match self.get(id) {
+            Some(ContainerNode::Window(_)) if id != self.root() => self.lower_at(id.index()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Stack the window at index above its siblings
+    fn raise_at(&mut self, index: usize) -> Result<(), Error> {
+        let window = self.tree[index].value.as_window_ref();
+        window.raise()?;
+
+        self.ewmh.raise(window.window())
+    }
+
+    /// Stack the window at index below its siblings
+    fn lower_at(&mut self, index: usize) -> Result<(), Error> {
+        let window = self.tree[index].value.as_window_ref();
+        window.lower()?;
+
+        self.ewmh.lower(window.window())
+    }
+
+    /// A property of a client window changed
+    pub fn property(&mut self, event: &x::PropertyNotifyEvent) -> Result<(), Error> {
+        let atoms = *self.conn.atoms();
+
+        let id = match self.from_window(event.window()) {
+            Some(id) if id.index() != self.tree.root() => id,
+            _ => return Ok(()),
+        };
+
+        let window = self.tree[id.index()].value.as_window_mut();
+
+        match event.atom() {
+            a if a == atoms.net_wm_window_type => {
+                let changed = window.load_type()?;
+
+                /* windows that were never mapped get their type once they are */
+                if changed && window.visible() {
+                    window.load_strut()?;
+                    self.apply_type(id.index())?;
+                    self.rearrange_outer(id.index())?;
+                }
+            }
+            a if a == atoms.net_wm_strut_partial || a == atoms.net_wm_strut => {
+                window.load_strut()?;
+            }
+            a if a == atoms.wm_protocols => {
+                window.load_protocols()?;
+            }
+            x::ATOM_WM_HINTS => {
+                window.load_hints()?;
+            }
+            x::ATOM_WM_CLASS => {
+                window.load_class()?;
+            }
+            a if a == x::ATOM_WM_NAME || a == atoms.net_wm_name => {
+                let changed = window.load_title()?;
+
+                if changed {
+                    self.conn.produce(Event::WindowTitle {
+                        window: id,
+                        title: window.title().unwrap_or_default().to_owned(),
+                    });
+                }
+            }
+            a if a == atoms.wm_window_role => {
+                window.load_role()?;
+            }
+            a if a == atoms.net_wm_pid => {
+                window.load_pid()?;
+            }
+            x::ATOM_WM_NORMAL_HINTS => {
+                let changed = window.load_size_hints()?;
+
+                if changed && window.visible() {
+                    self.rearrange_outer(id.index())?;
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Space reserved by every dock
+    pub fn struts(&self) -> impl Iterator<Item = Strut> + '_ {
+        self.tree.iter().filter_map(|(_, node)| match &node.value {
+            ContainerNode::Window(w) => w.strut(),
+            ContainerNode::Layout(_) => None,
+        })
+    }
+
+    /// Make a window cover its monitor, above everything else, or return it
+    /// to its layout. Produces a WindowFullscreen event on change
+    pub fn set_fullscreen(&mut self, id: ContainerId, fullscreen: bool) -> Result<(), Error> {
+        if !self.contains(id) || id.index() == self.tree.root() {
+            return Ok(());
+        }
+
+        let screen = self.screen(id.index());
+
+        let window = match &mut self.tree[id.index()].value {
+            ContainerNode::Window(window) if window.fullscreen() != fullscreen => window,
+            _ => return Ok(()),
+        };
+
+        window.set_fullscreen(fullscreen, screen)?;
+
+        if fullscreen {
+            self.raise_at(id.index())?;
+        }
+
+        self.conn.produce(Event::WindowFullscreen {
+            window: id,
+            fullscreen,
+        });
+
+        /* the layout may have changed while the window was away */
+        if !fullscreen {
+            self.rearrange_outer(id.index())?;
+        }
+
+        Ok(())
+    }
+
+    /// Requests clients and pagers send about a managed window
+    pub fn client_message(&mut self, event: &x::ClientMessageEvent) -> Result<(), Error> {
+        let atoms = *self.conn.atoms();
+
+        let data = match event.data() {
+            x::ClientMessageData::Data32(data) => data,
+            _ => return Ok(()),
+        };
+
+        let id = match self.from_window(event.window()) {
+            Some(id) if id.index() != self.tree.root() => id,
+            _ => return Ok(()),
+        };
+
+        match event.r#type() {
+            t if t == atoms.net_active_window => {
+                /* whether to honour it is up to the user */
+                /* clients following older versions of the spec send no time */
+                self.conn.produce(Event::ActivateRequest {
+                    window: id,
+                    source: ActivateSource::new(data[0]),
+                    time: Some(data[1]).filter(|time| *time != x::CURRENT_TIME),
+                });
+            }
+            /* data[0] is the action, data[1] and data[2] the states */
+            t if t == atoms.net_wm_state => {
+                let states = &data[1..3];
+                let window = self.tree[id.index()].value.as_window_ref();
+                let (fullscreen, minimized) = (window.fullscreen(), window.minimized());
+
+                if states.contains(&atoms.net_wm_state_fullscreen.resource_id()) {
+                    self.set_fullscreen(id, state_action(data[0], fullscreen))?;
+                }
+
+                if states.contains(&atoms.net_wm_state_hidden.resource_id()) {
+                    if state_action(data[0], minimized) {
+                        self.minimize(id)?;
+                    } else {
+                        self.restore(id)?;
+                    }
+                }
+            }
+            /* IconicState is the only change clients may ask for */
+            t if t == atoms.wm_change_state && data[0] == WmState::Iconic as u32 => {
+                self.minimize(id)?;
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Arrange this container by resizing all sub-windows according to their parent layouts
+    pub fn arrange(&mut self, scope: Rect) -> Result<(), Error> {
+        self.arrange_at(self.tree.root(), scope)
+    }
+
+    /// Re-arrange a layout node in the scope it was last arranged in
+    fn rearrange(&mut self, index: usize) -> Result<(), Error> {
+        let scope = match &self.tree[index].value {
+            ContainerNode::Layout(layout) => layout.scope,
+            ContainerNode::Window(_) => None,
+        };
+
+        match scope {
+            Some(size) => self.arrange_at(index, size),
+            None => Ok(()),
+        }
+    }
+
+    /// Remove a container and its children from the tree, re-arranging the
+    /// remaining siblings. Returns None if id is stale or the root, which
+    /// is never removed
+    pub fn remove(&mut self, id: ContainerId) -> Result<Option<ContainerNode>, Error> {
+        if !self.contains(id) || id.index() == self.tree.root() {
+            return Ok(None);
+        }
+
+        let parent = self.tree[id.index()].parent().expect("only the root has no parent");
+
+        let windows: Vec<_> = self.tree.iter_at(id.index())
+            .filter_map(|i| match &self.tree[i].value {
+                ContainerNode::Window(w) => Some(w.window()),
+                ContainerNode::Layout(_) => None,
+            })
+            .collect();
+
+        self.ewmh.remove_clients(&windows)?;
+
+        /* workspaces go with their layout */
+        let workspaces: Vec<_> = self.tree.iter_at(id.index())
+            .filter_map(|i| self.workspaces.by_layout(self.id(i)))
+            .collect();
+
+        for workspace in workspaces {
+            self.workspaces.remove(workspace);
+        }
+
+        self.unindex(id.index());
+        let node = self.tree.prune(id.index());
+
+        let tree = &self.tree;
+        let live = |id: &ContainerId| tree.contains(id.0);
+
+        self.minimized.retain(live);
+        self.transients.retain(|parent, transients| {
+            transients.retain(live);
+            live(parent) && !transients.is_empty()
+        });
+
+        match self.focused {
+            Some(focused) if !self.contains(focused) => {
+                /* focus falls back to the parent's most recent child */
+                let leaf = focus_leaf(&self.tree, parent);
+
+                if leaf == self.tree.root() {
+                    self.track(None)?;
+                    self.rearrange(parent)?;
+                } else {
+                    /* also re-arranges the layouts above leaf */
+                    self.focus(self.id(leaf), None)?;
+                }
+            }
+            _ => {
+                self.rearrange(parent)?;
+            }
+        }
+
+        Ok(Some(node))
+    }
+
+    pub fn workspaces(&self) -> &Workspaces {
+        &self.workspaces
+    }
+
+    pub fn ewmh(&self) -> &Ewmh {
+        &self.ewmh
+    }
+
+    pub(crate) fn workspaces_mut(&mut self) -> &mut Workspaces {
+        &mut self.workspaces
+    }
+
+    /// Add a workspace arranged by layout. It stays hidden until switched
+    /// to. The first workspace adopts the windows already shown on the root
+    pub fn create_workspace<L: layout::Layout + 'static>(&mut self, name: &str, layout: L) -> Result<WorkspaceId, Error> {
+        let root = self.tree.root();
+        let index = self.tree.insert(root, layout.into());
+        let id = self.workspaces.insert(name, self.id(index));
+
+        if self.workspaces.len() == 1 {
+            /* docks, desktops and the like stay on the root, but dialogs
+             * go along with the window they belong to */
+            let adopted: Vec<_> = self.tree.children(root)
+                .filter(|i| match &self.tree[*i].value {
+                    ContainerNode::Window(w) => {
+                        w.managed() && w.visible()
+                            && (w.window_type().tiled() || self.transient_parent(*i).is_some())
+                    }
+                    ContainerNode::Layout(_) => false,
+                })
+                .collect();
+
+            for i in adopted.into_iter() {
+                self.tree.move_to(i, index, usize::MAX);
+            }
+
+            /* not shown yet, so these must wait for the first arrangement */
+            self.hide(self.id(index))?;
+            self.tag(index);
+        }
+
+        Ok(id)
+    }
+
+    /// Rename a workspace. Returns false if id does not exist
+    pub fn rename_workspace(&mut self, id: WorkspaceId, name: &str) -> bool {
+        self.workspaces.rename(id, name)
+    }
+
+    /// Show a workspace arranged in scope, alongside those already shown
+    pub fn show_workspace(&mut self, id: WorkspaceId, scope: Rect) -> Result<(), Error> {
+        let (layout, visible) = match self.workspaces.get(id) {
+            Some(ws) => (ws.layout(), ws.visible()),
+            None => return Ok(()),
+        };
+
+        self.workspaces.set_visible(id, true);
+
+        if !visible {
+            self.show(layout)?;
+        }
+
+        self.arrange_at(layout.index(), scope)
+    }
+
+    /// Hide a workspace and everything on it
+    pub fn hide_workspace(&mut self, id: WorkspaceId) -> Result<(), Error> {
+        let layout = match self.workspaces.get(id) {
+            Some(ws) if ws.visible() => ws.layout(),
+            _ => return Ok(()),
+        };
+
+        self.workspaces.set_visible(id, false);
+        self.hide(layout)
+    }
+
+    /// Show id in place of the workspace shown on its monitor, arranged in
+    /// scope, and make it current. Focus returns to whatever id last focused
+    pub fn switch_workspace(&mut self, id: WorkspaceId, scope: Rect) -> Result<(), Error> {
+        let (layout, monitor) = match self.workspaces.get(id) {
+            Some(ws) => (ws.layout(), ws.monitor()),
+            None => return Ok(()),
+        };
+
+        let old = self.workspaces.current();
+
+        /* every monitor shows a single workspace */
+        let shown: Vec<_> = self.workspaces.iter()
+            .filter(|(other, ws)| *other != id && ws.visible() && ws.monitor() == monitor)
+            .map(|(other, _)| other)
+            .collect();
+
+        for other in shown.into_iter() {
+            self.hide_workspace(other)?;
+        }
+
+        self.workspaces.set_current(Some(id));
+        self.show_workspace(id, scope)?;
+
+        /* arranging focuses the workspace's last focus, if it has one */
+        match self.focused() {
+            Some(focused) if self.tree.is_ancestor(layout.index(), focused.index()) => {}
+            _ => self.focus_workspace(layout.index())?,
+        }
+
+        if old != Some(id) {
+            self.conn.produce(Event::WorkspaceSwitch {
+                old,
+                new: id,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Focus what the workspace layout at index focused last, or the
+    /// workspace itself when it is empty
+    fn focus_workspace(&mut self, index: usize) -> Result<(), Error> {
+        let leaf = focus_leaf(&self.tree, index);
+
+        if leaf != index {
+            return self.focus(self.id(leaf), None);
+        }
+
+        self.track(Some(self.id(index)))?;
+
+        /* nothing to give input focus to, so park it on the root */
+        let root = self.tree.root();
+        self.tree[root].value.as_window_ref().focus(self.time(None)?)
+    }
+
+    /// Move a container to the end of a workspace. If the container held the
+    /// focus, it stays on the current workspace
+    pub fn move_to_workspace(&mut self, id: ContainerId, workspace: WorkspaceId) -> Result<(), Error> {
+        let (layout, visible) = match self.workspaces.get(workspace) {
+            Some(ws) => (ws.layout(), ws.visible()),
+            None => return Ok(()),
+        };
+
+        /* workspaces themselves stay where they are */
+        if !self.contains(id) || id == self.root() || self.workspaces.by_layout(id).is_some() {
+            return Ok(());
+        }
+
+        if self.workspace_at(id.index()) == Some(workspace) {
+            return Ok(());
+        }
+
+        let focused = self.focused()
+            .is_some_and(|focused| self.tree.is_ancestor(id.index(), focused.index()));
+
+        /* dialogs go along with the window they belong to */
+        let transients = self.transients(id.index());
+
+        if !visible {
+            self.hide(id)?;
+        }
+
+        self.move_to(id, layout, usize::MAX)?;
+
+        for index in transients {
+            let transient = self.id(index);
+
+            if !visible {
+                self.hide(transient)?;
+            }
+
+            self.move_to(transient, layout, usize::MAX)?;
+        }
+
+        let current = self.workspaces.current()
+            .and_then(|ws| self.workspaces.get(ws))
+            .map(|ws| ws.layout());
+
+        match current {
+            Some(current) if focused && current != layout => self.focus_workspace(current.index()),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn show(&mut self, id: ContainerId) -> Result<(), Error> {
+        if !self.contains(id) {
+            return Ok(());
+        }
+
+        let indices: Vec<_> = self.tree.iter_at(id.index()).collect();
+
+        for i in indices.into_iter() {
+            match &mut self.tree[i].value {
+                ContainerNode::Window(w) if !w.minimized() => { w.show()?; }
+                _ => {},
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Minimized windows, most recently minimized first
+    pub fn minimized(&self) -> impl Iterator<Item = ContainerId> + '_ {
+        self.minimized.iter().rev().copied()
+    }
+
+    /// Hide a managed window and leave it out of arrangement until it is
+    /// restored. Focus moves on to what its layout focused before
+    pub fn minimize(&mut self, id: ContainerId) -> Result<(), Error> {
+        if !self.contains(id) || id == self.root() {
+            return Ok(());
+        }
+
+        match &mut self.tree[id.index()].value {
+            ContainerNode::Window(w) if w.managed() && !w.minimized() => w.set_minimized(true)?,
+            _ => return Ok(()),
+        }
+
+        self.minimized.push(id);
+        self.rearrange_outer(id.index())?;
+
+        if self.focused() != Some(id) {
+            return Ok(());
+        }
+
+        match self.workspace_at(id.index()).and_then(|ws| self.workspaces.get(ws)) {
+            Some(ws) if ws.visible() => self.focus_workspace(ws.layout().index()),
+            _ => self.track(None),
+        }
+    }
+
+    /// Bring back a minimized window and focus it
+    pub fn restore(&mut self, id: ContainerId) -> Result<(), Error> {
+        if !self.minimized.contains(&id) {
+            return Ok(());
+        }
+
+        self.minimized.retain(|m| *m != id);
+        self.tree[id.index()].value.as_window_mut().set_minimized(false)?;
+
+        /* windows outside of layouts are not shown by arranging */
+        if !self.hidden(id.index()) {
+            self.tree[id.index()].value.as_window_mut().show()?;
+        }
+
+        self.rearrange_outer(id.index())?;
+        self.focus(id, None)
+    }
+
+    /// Restore the most recently minimized window
+    pub fn restore_last(&mut self) -> Result<(), Error> {
+        match self.minimized.last() {
+            Some(id) => self.restore(*id),
+            None => Ok(()),
+        }
+    }
+
+    pub fn hide(&mut self, id: ContainerId) -> Result<(), Error> {
+        if !self.contains(id) {
+            return Ok(());
+        }
+
+        let indices: Vec<_> = self.tree.iter_at(id.index()).collect();
+
+        for i in indices.into_iter() {
+            match &mut self.tree[i].value {
+                ContainerNode::Window(w) => { w.hide()?; }
+                ContainerNode::Layout(_) => {},
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Insert a new window node and announce it with a WindowCreate event
+    fn manage(&mut self, parent: ContainerId, window: x::Window, size: Rect, managed: bool) -> Result<ContainerId, Error> {
+        let win = Window::new(self.conn.clone(), window, size, managed, true);
+        let index = self.tree.insert(parent.index(), win.into());
+        let id = self.id(index);
+
+        self.windows.insert(window, id);
+        self.tag(index);
+
+        self.conn.produce(Event::WindowCreate {
+            window: id,
+            x: size.x,
+            y: size.y,
+            width: size.w,
+            height: size.h,
+        });
+
+        Ok(id)
+    }
+
+    /// Adopt the viewable windows already on the root window, e.g. when the
+    /// manager is restarted. Each produces a WindowCreate and a WindowShow
+    pub fn scan(&mut self) -> Result<(), Error> {
+        let cookie = self.conn.send_request(&x::QueryTree {
+            window: self.conn.root(),
+        });
+
+        let reply = self.conn.wait_for_reply(cookie)?;
+
+        /* send every request up front, then collect the replies */
+        let cookies: Vec<_> = reply.children().iter().map(|window| {
+            let attributes = self.conn.send_request(&x::GetWindowAttributes {
+                window: *window,
+            });
+
+            let geometry = self.conn.send_request(&x::GetGeometry {
+                drawable: x::Drawable::Window(*window),
+            });
+
+            (*window, attributes, geometry)
+        }).collect();
+
+        let root = self.root();
+        let mut adopted = vec![];
+
+        for (window, attributes, geometry) in cookies {
+            /* windows may be destroyed between QueryTree and now */
+            let attributes = match self.conn.wait_for_reply(attributes) {
+                Ok(reply) => reply,
+                Err(_) => continue,
+            };
+
+            let geometry = match self.conn.wait_for_reply(geometry) {
+                Ok(reply) => reply,
+                Err(_) => continue,
+            };
+
+            if attributes.override_redirect() || attributes.map_state() != x::MapState::Viewable {
+                continue;
+            }
+
+            let size = Rect::new(geometry.x(), geometry.y(), geometry.width(), geometry.height());
+            let id = self.manage(root, window, size, true)?;
+
+            /* a window gone by now is removed on its DestroyNotify */
+            if !self.tree[id.index()].value.as_window_mut().load()? {
+                continue;
+            }
+
+            self.tree[id.index()].value.as_window_mut().mapped()?;
+            self.ewmh.add_client(window)?;
+
+            adopted.push(id);
+        }
+
+        /* only once every window is known, as transients may come first */
+        for id in adopted {
+            self.apply_policy(id)?;
+
+            self.conn.produce(Event::WindowShow {
+                window: id
+            });
+        }
+
+        Ok(())
+    }
+
+    pub fn create(&mut self, event: &x::CreateNotifyEvent) -> Result<(), Error> {
+        /* our own EWMH check window is never managed */
+        if event.window() == self.ewmh.check() {
+            return Ok(());
+        }
+
+        let parent = self.from_window(event.parent())
+            .unwrap_or(self.root());
+
+        let size = Rect::new(event.x(), event.y(), event.width(), event.height());
+        self.manage(parent, event.window(), size, !event.override_redirect())?;
+
+        Ok(())
+    }
+
+    pub fn destroy(&mut self, event: &x::DestroyNotifyEvent) -> Result<(), Error> {
+        match self.from_window(event.window()) {
+            Some(id) if id.index() != self.tree.root() => {
+                self.remove(id)?;
+                self.conn.produce(Event::WindowDestroy {
+                    window: id
+                });
+            },
+            _ => {
+                /* already withdrawn through unmap(), or never known */
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn unmap(&mut self, event: &x::UnmapNotifyEvent) -> Result<(), Error> {
+        match self.from_window(event.window()) {
+            Some(id) if id.index() != self.tree.root() => {
+                /* unmaps we caused through Window::hide keep the node */
+                if self.tree[id.index()].value.as_window_mut().expect_unmap() {
+                    return Ok(());
+                }
+
+                /* the client withdrew the window. it is managed again from
+                 * scratch if it ever sends another MapRequest */
+                /* the window may be destroyed already, leaving no state to set */
+                match self.tree[id.index()].value.as_window_ref().set_wm_state(WmState::Withdrawn) {
+                    Err(e) if !e.bad_window() => return Err(e),
+                    _ => {}
+                }
+                self.remove(id)?;
+                self.conn.produce(Event::WindowHide {
+                    window: id
+                });
+            },
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    pub fn configure(&mut self, event: &x::ConfigureRequestEvent) {
+        match self.from_window(event.window()) {
+            Some(id) => {
+                self.conn.produce(Event::WindowResize {
+                    window: id,
+                    x: event.x(),
+                    y: event.y(),
+                    width: event.width(),
+                    height: event.height(),
+                });
+            },
+            None => {
+                eprintln!("Unknown window configured!");
+            }
+        }
+    }
+
+    pub fn map(&mut self, event: &x::MapRequestEvent) -> Result<(), Error> {
+        let id = match self.from_window(event.window()) {
+            Some(id) => id,
+            None => {
+                /* a previously withdrawn window is being mapped again */
+                let size = match get_window_rect(self.conn.raw(), event.window()) {
+                    Ok(size) => size,
+                    Err(e) if e.bad_window() => return Ok(()),
+                    Err(e) => return Err(e),
+                };
+                let parent = self.root();
+
+                self.manage(parent, event.window(), size, true)?
+            }
+        };
+
+        /* clients set their properties before mapping, so creation is too
+         * early to read them. a window gone by now is removed on its
+         * DestroyNotify */
+        if !self.tree[id.index()].value.as_window_mut().load()? {
+            return Ok(());
+        }
+
+        if self.tree[id.index()].value.as_window_ref().managed() {
+            self.ewmh.add_client(event.window())?;
+        }
+
+        /* mapping an iconic window is how a client restores it */
+        if self.minimized.contains(&id) {
+            self.minimized.retain(|m| *m != id);
+            self.tree[id.index()].value.as_window_mut().set_minimized(false)?;
+        }
+
+        self.apply_policy(id)?;
+
+        self.conn.produce(Event::WindowShow {
+            window: id
+        });
+
+        Ok(())
+    }
+
+    /// Place a window that is being shown according to its type, the window
+    /// it is a transient of and the states it asked for before mapping
+    fn apply_policy(&mut self, id: ContainerId) -> Result<(), Error> {
+        self.apply_type(id.index())?;
+        self.apply_transient(id.index())?;
+
+        /* top-level windows join the current workspace once mapped */
+        let current = self.workspaces.current()
+            .and_then(|ws| self.workspaces.get(ws))
+            .map(|ws| ws.layout());
+
+        let tiled = self.tree[id.index()].value.as_window_ref().window_type().tiled();
+
+        if let (Some(layout), true) = (current, tiled) {
+            if self.tree[id.index()].parent() == Some(self.tree.root()) {
+                self.tree.move_to(id.index(), layout.index(), usize::MAX);
+                self.tag(id.index());
+            }
+        }
+
+        /* clients may ask for fullscreen before they are mapped */
+        let states = self.tree[id.index()].value.as_window_ref().requested_states()?;
+
+        if states.contains(&self.conn.atoms().net_wm_state_fullscreen) {
+            self.set_fullscreen(id, true)?;
+        }
+
+        Ok(())
     }
 }
 
-impl std::ops::IndexMut<ContainerId> for Container {
-    fn index_mut(&mut self, id: ContainerId) -> &mut Self::Output {
-        &mut self.tree[id.id].value
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::{Cell, Monacle, Tile, Orientation};
+
+    enum Node {
+        Layout(Layout),
+        Leaf,
+        Minimized,
+    }
+
+    impl Arrange for Node {
+        fn layout(&self) -> Option<&Layout> {
+            match self {
+                Node::Layout(layout) => Some(layout),
+                _ => None,
+            }
+        }
+
+        fn layout_mut(&mut self) -> Option<&mut Layout> {
+            match self {
+                Node::Layout(layout) => Some(layout),
+                _ => None,
+            }
+        }
+
+        fn describe(&self) -> layout::Child {
+            match self {
+                Node::Layout(layout) => layout.describe(),
+                _ => layout::Child::new(Rect::new(0, 0, 0, 0)),
+            }
+        }
+
+        fn arranged(&self) -> bool {
+            !matches!(self, Node::Minimized)
+        }
+    }
+
+    fn layout<L: layout::Layout + 'static>(l: L) -> Node {
+        Node::Layout(Layout::new(l))
+    }
+
+    fn tile() -> Node {
+        layout(Tile::new(1, 0.5, Orientation::Left))
+    }
+
+    /// Arrange from the root, returning (focused leaf, shown leaves)
+    fn arrange(tree: &mut Tree<Node>) -> (Option<usize>, Vec<usize>) {
+        let mut cells = vec![];
+        plan(tree, tree.root(), Rect::new(0, 0, 100, 100), true, &mut cells);
+
+        let mut focused = None;
+        let mut shown = vec![];
+
+        for (leaf, cell) in cells {
+            match cell {
+                Cell::Hide => {},
+                Cell::Show(_) => shown.push(leaf),
+                Cell::Focus(_) => {
+                    assert!(focused.is_none(), "more than one focused leaf");
+                    focused = Some(leaf);
+                    shown.push(leaf);
+                }
+            }
+        }
+
+        shown.sort();
+        (focused, shown)
+    }
+
+    /* tile
+     * ├── a
+     * └── monacle
+     *     ├── b
+     *     └── tile
+     *         ├── c
+     *         └── d */
+    fn nested() -> (Tree<Node>, [usize; 6]) {
+        let mut tree = Tree::new(tile());
+        let root = tree.root();
+
+        let a = tree.insert(root, Node::Leaf);
+        let monacle = tree.insert(root, layout(Monacle::new()));
+        let b = tree.insert(monacle, Node::Leaf);
+        let inner = tree.insert(monacle, tile());
+        let c = tree.insert(inner, Node::Leaf);
+        let d = tree.insert(inner, Node::Leaf);
+
+        (tree, [a, monacle, b, inner, c, d])
+    }
+
+    #[test]
+    fn test_focus_nested() {
+        let (mut tree, [a, _, b, _, c, d]) = nested();
+
+        set_focus_path(&mut tree, c);
+        assert_eq!(arrange(&mut tree), (Some(c), vec![a, c, d]));
+
+        set_focus_path(&mut tree, b);
+        assert_eq!(arrange(&mut tree), (Some(b), vec![a, b]));
+
+        set_focus_path(&mut tree, a);
+        assert_eq!(arrange(&mut tree), (Some(a), vec![a, b]));
+
+        /* the monacle still remembers b */
+        assert!(on_focus_path(&tree, a));
+        assert!(!on_focus_path(&tree, b));
+    }
+
+    #[test]
+    fn test_focus_layout() {
+        let (mut tree, [a, monacle, _, inner, c, d]) = nested();
+
+        /* focusing a layout ends on the leaf its focus path leads to */
+        set_focus_path(&mut tree, c);
+        set_focus_path(&mut tree, a);
+        set_focus_path(&mut tree, monacle);
+        assert_eq!(arrange(&mut tree), (Some(c), vec![a, c, d]));
+
+        set_focus_path(&mut tree, d);
+        set_focus_path(&mut tree, inner);
+        assert_eq!(arrange(&mut tree), (Some(d), vec![a, c, d]));
+    }
+
+    #[test]
+    fn test_focus_default() {
+        let (mut tree, [a, _, _, _, c, d]) = nested();
+
+        /* without any focus, every layout focuses its last child */
+        assert_eq!(arrange(&mut tree), (Some(d), vec![a, c, d]));
+
+        /* a removed focused child falls back the same way */
+        set_focus_path(&mut tree, d);
+        tree.prune(d);
+        assert_eq!(arrange(&mut tree), (Some(c), vec![a, c]));
+    }
+
+    #[test]
+    fn test_focus_minimized() {
+        let (mut tree, [a, _, _, _, c, d]) = nested();
+
+        /* minimized children are left out, and the focus moves on */
+        set_focus_path(&mut tree, d);
+        tree[d].value = Node::Minimized;
+        assert_eq!(arrange(&mut tree), (Some(c), vec![a, c]));
+
+        let root = tree.root();
+        let mut cells = vec![];
+        plan(&mut tree, root, Rect::new(0, 0, 100, 100), true, &mut cells);
+        assert!(cells.iter().all(|(leaf, _)| *leaf != d));
+
+        /* once restored, the focus history still has it */
+        tree[d].value = Node::Leaf;
+        assert_eq!(arrange(&mut tree), (Some(d), vec![a, c, d]));
+    }
+
+    #[test]
+    fn test_focus_history() {
+        let mut tree = Tree::new(layout(Monacle::new()));
+        let root = tree.root();
+        let leaves: Vec<_> = (0..4).map(|_| tree.insert(root, Node::Leaf)).collect();
+
+        for i in [1, 3, 0, 2] {
+            set_focus_path(&mut tree, leaves[i]);
+        }
+
+        assert_eq!(history(&tree, root).collect::<Vec<_>>(), vec![leaves[2], leaves[0], leaves[3], leaves[1]]);
+
+        /* removing the focused child falls back to the previous focus,
+         * not whatever happens to be last */
+        tree.prune(leaves[2]);
+        assert_eq!(arrange(&mut tree), (Some(leaves[0]), vec![leaves[0]]));
+
+        /* a new node reusing a removed index is not part of the history */
+        tree.prune(leaves[0]);
+        let new = tree.insert(root, Node::Leaf);
+        assert_eq!(new, leaves[0]);
+        assert_eq!(history(&tree, root).collect::<Vec<_>>(), vec![leaves[3], leaves[1]]);
+    }
+
+    #[test]
+    fn test_last_focus() {
+        let (mut tree, [a, monacle, b, inner, c, d]) = nested();
+
+        assert_eq!(last_focus(&tree, c), None);
+
+        set_focus_path(&mut tree, c);
+        set_focus_path(&mut tree, d);
+        assert_eq!(last_focus(&tree, d), Some(c));
+
+        /* nothing else was focused in b's monacle, so look further out */
+        set_focus_path(&mut tree, a);
+        set_focus_path(&mut tree, b);
+        assert_eq!(last_focus(&tree, b), Some(inner));
+        assert_eq!(focus_leaf(&tree, inner), d);
+
+        set_focus_path(&mut tree, monacle);
+        assert_eq!(last_focus(&tree, monacle), Some(a));
+    }
+
+    #[test]
+    fn test_sibling() {
+        let (tree, [a, monacle, b, inner, _, _]) = nested();
+
+        assert_eq!(sibling(&tree, a, true), Some(monacle));
+        assert_eq!(sibling(&tree, monacle, true), Some(a));
+        assert_eq!(sibling(&tree, a, false), Some(monacle));
+        assert_eq!(sibling(&tree, b, false), Some(inner));
+        assert_eq!(sibling(&tree, tree.root(), true), None);
+
+        let mut single = Tree::new(tile());
+        let only = single.insert(single.root(), Node::Leaf);
+        assert_eq!(sibling(&single, only, true), None);
+    }
+
+    #[test]
+    fn test_sibling_minimized() {
+        let mut tree = Tree::new(tile());
+        let root = tree.root();
+        let a = tree.insert(root, Node::Leaf);
+        let b = tree.insert(root, Node::Minimized);
+        let c = tree.insert(root, Node::Leaf);
+
+        /* b can not take the focus, so cycling steps over it */
+        assert_eq!(sibling(&tree, a, true), Some(c));
+        assert_eq!(sibling(&tree, c, false), Some(a));
+        assert_eq!(sibling(&tree, c, true), Some(a));
+        assert_eq!(sibling(&tree, b, true), Some(c));
+
+        tree[c].value = Node::Minimized;
+        assert_eq!(sibling(&tree, a, true), None);
+    }
+
+    #[test]
+    fn test_focus_deep() {
+        let mut tree = Tree::new(tile());
+        let mut parent = tree.root();
+        let mut leaves = vec![];
+
+        for _ in 0..5 {
+            leaves.push(tree.insert(parent, Node::Leaf));
+            parent = tree.insert(parent, layout(Monacle::new()));
+        }
+
+        let last = tree.insert(parent, Node::Leaf);
+
+        set_focus_path(&mut tree, last);
+        assert_eq!(arrange(&mut tree), (Some(last), vec![leaves[0], last]));
+
+        set_focus_path(&mut tree, leaves[3]);
+        assert_eq!(arrange(&mut tree), (Some(leaves[3]), vec![leaves[0], leaves[3]]));
+    }
+
+    #[test]
+    fn test_unfocused_plan() {
+        let (mut tree, [a, _, _, _, c, d]) = nested();
+
+        set_focus_path(&mut tree, c);
+
+        let mut cells = vec![];
+        plan(&mut tree, 0, Rect::new(0, 0, 100, 100), false, &mut cells);
+
+        assert!(cells.iter().all(|(_, cell)| !matches!(cell, Cell::Focus(_))));
+        assert_eq!(cells.iter().filter(|(_, c)| matches!(c, Cell::Show(_))).count(), 3);
+        assert!([a, c, d].iter().all(|i| cells.iter().any(|(j, _)| i == j)));
     }
 }
@@ -1,4 +1,5 @@
 use thiserror::Error;
+use xcb::x;
 
 #[derive(Error, Debug)]
 pub enum Error {
@@ -13,3 +14,16 @@ pub enum Error {
     #[error("io error")]
     IoError(#[from] std::io::Error),
 }
+
+impl Error {
+    /// Whether the error is a BadWindow, i.e. the window no longer exists
+    pub fn bad_window(&self) -> bool {
+        let protocol = match self {
+            Error::ProtocolError(e) => e,
+            Error::XCBError(xcb::Error::Protocol(e)) => e,
+            _ => return false,
+        };
+
+        matches!(protocol, xcb::ProtocolError::X(x::Error::Window(_), _))
+    }
+}
@@ -0,0 +1,411 @@
+use xcb::{x, Xid};
+
+use crate::rect::Rect;
+use crate::error::Error;
+use crate::manager::Connection;
+
+/// Name advertised to EWMH clients
+const NAME: &str = "ndwm";
+
+/// Who asked for a window to be activated
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ActivateSource {
+    /// A client using an older version of the spec
+    Unknown,
+    Application,
+    /// A pager, taskbar or other direct user action
+    Pager,
+}
+
+impl ActivateSource {
+    /// Decode the source indication of a client message
+    pub(crate) fn new(source: u32) -> Self {
+        match source {
+            1 => ActivateSource::Application,
+            2 => ActivateSource::Pager,
+            _ => ActivateSource::Unknown,
+        }
+    }
+}
+
+/// Space a dock reserves along the edges of the root window, as given by
+/// _NET_WM_STRUT_PARTIAL. Each edge applies to a range of the other axis
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct Strut {
+    pub left: u32,
+    pub right: u32,
+    pub top: u32,
+    pub bottom: u32,
+    pub left_start_y: u32,
+    pub left_end_y: u32,
+    pub right_start_y: u32,
+    pub right_end_y: u32,
+    pub top_start_x: u32,
+    pub top_end_x: u32,
+    pub bottom_start_x: u32,
+    pub bottom_end_x: u32,
+}
+
+impl Strut {
+    /// Decode _NET_WM_STRUT_PARTIAL, or the older _NET_WM_STRUT whose edges
+    /// span the whole root window
+    pub fn new(values: &[u32]) -> Option<Self> {
+        match values.len() {
+            12 => Some(Strut {
+                left: values[0],
+                right: values[1],
+                top: values[2],
+                bottom: values[3],
+                left_start_y: values[4],
+                left_end_y: values[5],
+                right_start_y: values[6],
+                right_end_y: values[7],
+                top_start_x: values[8],
+                top_end_x: values[9],
+                bottom_start_x: values[10],
+                bottom_end_x: values[11],
+            }),
+            4 => Some(Strut {
+                left: values[0],
+                right: values[1],
+                top: values[2],
+                bottom: values[3],
+                left_end_y: u32::MAX,
+                right_end_y: u32::MAX,
+                top_end_x: u32::MAX,
+                bottom_end_x: u32::MAX,
+                ..Default::default()
+            }),
+            _ => None,
+        }
+    }
+
+    /// Shrink area, a part of root, so it stays clear of the strut
+    pub fn reserve(&self, area: Rect, root: Rect) -> Rect {
+        let (x0, y0) = (area.left() as i64, area.top() as i64);
+        let (x1, y1) = (area.right() as i64, area.bottom() as i64);
+
+        /* the inclusive range start..=end reaches into lo..hi */
+        let overlaps = |start: u32, end: u32, lo: i64, hi: i64| {
+            (start as i64) < hi && (end as i64) >= lo
+        };
+
+        let (mut left, mut top, mut right, mut bottom) = (x0, y0, x1, y1);
+
+        if self.left > 0 && overlaps(self.left_start_y, self.left_end_y, y0, y1) {
+            left = left.max(root.left() as i64 + self.left as i64);
+        }
+
+        if self.right > 0 && overlaps(self.right_start_y, self.right_end_y, y0, y1) {
+            right = right.min(root.right() as i64 - self.right as i64);
+        }
+
+        if self.top > 0 && overlaps(self.top_start_x, self.top_end_x, x0, x1) {
+            top = top.max(root.top() as i64 + self.top as i64);
+        }
+
+        if self.bottom > 0 && overlaps(self.bottom_start_x, self.bottom_end_x, x0, x1) {
+            bottom = bottom.min(root.bottom() as i64 - self.bottom as i64);
+        }
+
+        /* a strut wider than the area leaves nothing rather than less */
+        let left = left.min(x1);
+        let top = top.min(y1);
+        let right = right.max(left);
+        let bottom = bottom.max(top);
+
+        Rect::new(left as i16, top as i16, (right - left) as u16, (bottom - top) as u16)
+    }
+}
+
+/// Root window state shared with panels, pagers and other EWMH clients
+pub struct Ewmh {
+    conn: Connection,
+    /* the _NET_SUPPORTING_WM_CHECK child window */
+    check: x::Window,
+    /* managed clients, oldest first */
+    clients: Vec<x::Window>,
+    /* managed clients, bottom to top */
+    stacking: Vec<x::Window>,
+}
+
+impl Ewmh {
+    /// Create the supporting check window and publish what the manager
+    /// supports. Must only be called once the manager owns the root window
+    pub fn new(conn: Connection) -> Result<Self, Error> {
+        let check = conn.raw().generate_id();
+
+        let cookie = conn.send_request_checked(&x::CreateWindow {
+            depth: x::COPY_FROM_PARENT as u8,
+            wid: check,
+            parent: conn.root(),
+            x: -1,
+            y: -1,
+            width: 1,
+            height: 1,
+            border_width: 0,
+            class: x::WindowClass::InputOnly,
+            visual: x::COPY_FROM_PARENT,
+            value_list: &[
+                x::Cw::OverrideRedirect(true),
+                /* see time() */
+                x::Cw::EventMask(x::EventMask::PROPERTY_CHANGE),
+            ],
+        });
+
+        conn.check_request(cookie)?;
+
+        let ewmh = Ewmh {
+            conn,
+            check,
+            clients: vec![],
+            stacking: vec![],
+        };
+
+        let atoms = *ewmh.conn.atoms();
+
+        /* both the root and the check window point at the check window */
+        ewmh.set_windows(ewmh.conn.root(), atoms.net_supporting_wm_check, &[check])?;
+        ewmh.set_windows(check, atoms.net_supporting_wm_check, &[check])?;
+
+        let cookie = ewmh.conn.send_request_checked(&x::ChangeProperty {
+            mode: x::PropMode::Replace,
+            window: check,
+            property: atoms.net_wm_name,
+            r#type: atoms.utf8_string,
+            data: NAME.as_bytes(),
+        });
+
+        ewmh.conn.check_request(cookie)?;
+
+        let cookie = ewmh.conn.send_request_checked(&x::ChangeProperty {
+            mode: x::PropMode::Replace,
+            window: ewmh.conn.root(),
+            property: atoms.net_supported,
+            r#type: x::ATOM_ATOM,
+            data: &ewmh.supported(),
+        });
+
+        ewmh.conn.check_request(cookie)?;
+
+        ewmh.update_clients()?;
+        ewmh.set_active(None)?;
+
+        Ok(ewmh)
+    }
+
+    /// The _NET_SUPPORTING_WM_CHECK window
+    pub fn check(&self) -> x::Window {
+        self.check
+    }
+
+    /// Current server time, for requests not caused by an event that
+    /// carries one. Appending nothing to a property of the check window
+    /// makes the server report the time in a PropertyNotify
+    pub(crate) fn time(&self) -> Result<x::Timestamp, Error> {
+        let atoms = self.conn.atoms();
+
+        let cookie = self.conn.send_request_checked(&x::ChangeProperty {
+            mode: x::PropMode::Append,
+            window: self.check,
+            property: atoms.net_wm_name,
+            r#type: atoms.utf8_string,
+            data: &[] as &[u8],
+        });
+
+        self.conn.check_request(cookie)?;
+
+        self.conn.wait_for_property(self.check, atoms.net_wm_name)
+    }
+
+    /// Managed clients, in the order they were first mapped
+    pub fn clients(&self) -> &[x::Window] {
+        &self.clients
+    }
+
+    /// Managed clients, from the bottom of the stack to the top
+    pub fn stacking(&self) -> &[x::Window] {
+        &self.stacking
+    }
+
+    /// Hints advertised in _NET_SUPPORTED. Only list what is implemented
+    fn supported(&self) -> Vec<x::Atom> {
+        let atoms = self.conn.atoms();
+
+        vec![
+            atoms.net_supported,
+            atoms.net_supporting_wm_check,
+            atoms.net_wm_name,
+            atoms.net_client_list,
+            atoms.net_client_list_stacking,
+            atoms.net_active_window,
+            atoms.net_close_window,
+            atoms.net_wm_state,
+            atoms.net_wm_state_fullscreen,
+            atoms.net_wm_state_hidden,
+            atoms.net_wm_window_type,
+            atoms.net_wm_window_type_normal,
+            atoms.net_wm_window_type_dialog,
+            atoms.net_wm_window_type_dock,
+            atoms.net_wm_window_type_splash,
+            atoms.net_wm_window_type_utility,
+            atoms.net_wm_window_type_toolbar,
+            atoms.net_wm_window_type_notification,
+            atoms.net_wm_window_type_desktop,
+            atoms.net_wm_strut,
+            atoms.net_wm_strut_partial,
+            atoms.net_workarea,
+        ]
+    }
+
+    /// Add a client to the client lists, if it is not on them yet
+    pub(crate) fn add_client(&mut self, window: x::Window) -> Result<(), Error> {
+        if self.clients.contains(&window) {
+            return Ok(());
+        }
+
+        /* newly mapped windows start at the top of the stack */
+        self.clients.push(window);
+        self.stacking.push(window);
+        self.update_clients()
+    }
+
+    /// Remove clients from the client lists
+    pub(crate) fn remove_clients(&mut self, windows: &[x::Window]) -> Result<(), Error> {
+        let count = self.clients.len();
+        self.clients.retain(|w| !windows.contains(w));
+        self.stacking.retain(|w| !windows.contains(w));
+
+        if self.clients.len() != count {
+            self.update_clients()?;
+        }
+
+        Ok(())
+    }
+
+    /// Record a client as raised to the top of the stack
+    pub(crate) fn raise(&mut self, window: x::Window) -> Result<(), Error> {
+        self.restack(window, self.stacking.len())
+    }
+
+    /// Record a client as lowered to the bottom of the stack
+    pub(crate) fn lower(&mut self, window: x::Window) -> Result<(), Error> {
+        self.restack(window, 0)
+    }
+
+    /// Publish the window holding the focus, if any
+    pub(crate) fn set_active(&self, window: Option<x::Window>) -> Result<(), Error> {
+        let window = window.unwrap_or(x::Window::none());
+        self.set_windows(self.conn.root(), self.conn.atoms().net_active_window, &[window])
+    }
+
+    /// Publish the usable area of each workspace as _NET_WORKAREA
+    pub(crate) fn set_workarea(&self, areas: &[Rect]) -> Result<(), Error> {
+        let data: Vec<u32> = areas.iter()
+            .flat_map(|a| [a.x as u32, a.y as u32, a.w as u32, a.h as u32])
+            .collect();
+
+        let cookie = self.conn.send_request_checked(&x::ChangeProperty {
+            mode: x::PropMode::Replace,
+            window: self.conn.root(),
+            property: self.conn.atoms().net_workarea,
+            r#type: x::ATOM_CARDINAL,
+            data: &data,
+        });
+
+        self.conn.check_request(cookie)?;
+
+        Ok(())
+    }
+
+    /// Move a client to position in the stacking list. Windows that are not
+    /// clients are left alone
+    fn restack(&mut self, window: x::Window, position: usize) -> Result<(), Error> {
+        let current = match self.stacking.iter().position(|w| *w == window) {
+            Some(current) => current,
+            None => return Ok(()),
+        };
+
+        let position = position.min(self.stacking.len() - 1);
+
+        if current == position {
+            return Ok(());
+        }
+
+        self.stacking.remove(current);
+        self.stacking.insert(position, window);
+
+        self.set_windows(self.conn.root(), self.conn.atoms().net_client_list_stacking, &self.stacking)
+    }
+
+    fn update_clients(&self) -> Result<(), Error> {
+        let atoms = self.conn.atoms();
+
+        self.set_windows(self.conn.root(), atoms.net_client_list, &self.clients)?;
+        self.set_windows(self.conn.root(), atoms.net_client_list_stacking, &self.stacking)?;
+
+        Ok(())
+    }
+
+    fn set_windows(&self, window: x::Window, property: x::Atom, windows: &[x::Window]) -> Result<(), Error> {
+        let cookie = self.conn.send_request_checked(&x::ChangeProperty {
+            mode: x::PropMode::Replace,
+            window,
+            property,
+            r#type: x::ATOM_WINDOW,
+            data: windows,
+        });
+
+        self.conn.check_request(cookie)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod strut_tests {
+    use super::*;
+
+    #[test]
+    fn test_full_strut() {
+        let root = Rect::new(0, 0, 1920, 1080);
+        let strut = Strut::new(&[0, 0, 30, 0]).unwrap();
+
+        assert_eq!(strut.reserve(root, root), Rect::new(0, 30, 1920, 1050));
+    }
+
+    #[test]
+    fn test_partial_strut() {
+        /* two monitors side by side, a bottom bar on the right one */
+        let root = Rect::new(0, 0, 3840, 1080);
+        let left = Rect::new(0, 0, 1920, 1080);
+        let right = Rect::new(1920, 0, 1920, 1080);
+
+        let strut = Strut::new(&[0, 0, 0, 40, 0, 0, 0, 0, 0, 0, 1920, 3839]).unwrap();
+
+        assert_eq!(strut.reserve(left, root), left);
+        assert_eq!(strut.reserve(right, root), Rect::new(1920, 0, 1920, 1040));
+    }
+
+    #[test]
+    fn test_strut_other_monitor() {
+        let root = Rect::new(0, 0, 3840, 1080);
+        let left = Rect::new(0, 0, 1920, 1080);
+        let right = Rect::new(1920, 0, 1920, 1080);
+
+        /* struts are measured from the root window edges */
+        let strut = Strut::new(&[0, 50, 0, 0]).unwrap();
+        assert_eq!(strut.reserve(left, root), left);
+        assert_eq!(strut.reserve(right, root), Rect::new(1920, 0, 1870, 1080));
+
+        let strut = Strut::new(&[1950, 0, 0, 0]).unwrap();
+        assert_eq!(strut.reserve(left, root), Rect::new(1920, 0, 0, 1080));
+        assert_eq!(strut.reserve(right, root), Rect::new(1950, 0, 1890, 1080));
+    }
+
+    #[test]
+    fn test_invalid_strut() {
+        assert_eq!(Strut::new(&[]), None);
+        assert_eq!(Strut::new(&[1, 2, 3]), None);
+    }
+}
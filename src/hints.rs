@@ -0,0 +1,239 @@
+use xcb::x;
+
+use crate::rect::Size;
+
+/* WM_SIZE_HINTS flags, ICCCM 4.1.2.3 */
+const P_MIN_SIZE: u32 = 1 << 4;
+const P_MAX_SIZE: u32 = 1 << 5;
+const P_RESIZE_INC: u32 = 1 << 6;
+const P_ASPECT: u32 = 1 << 7;
+const P_BASE_SIZE: u32 = 1 << 8;
+const P_WIN_GRAVITY: u32 = 1 << 9;
+
+/// Client preferences for its window size, from WM_NORMAL_HINTS. Aspect
+/// ratios are (numerator, denominator) pairs of width over height
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SizeHints {
+    pub min: Option<Size>,
+    pub max: Option<Size>,
+    pub base: Option<Size>,
+    pub increment: Option<Size>,
+    pub min_aspect: Option<(u32, u32)>,
+    pub max_aspect: Option<(u32, u32)>,
+    pub gravity: x::Gravity,
+}
+
+impl Default for SizeHints {
+    fn default() -> Self {
+        SizeHints {
+            min: None,
+            max: None,
+            base: None,
+            increment: None,
+            min_aspect: None,
+            max_aspect: None,
+            gravity: x::Gravity::NorthWest,
+        }
+    }
+}
+
+impl SizeHints {
+    /// Decode a WM_SIZE_HINTS property. Missing or malformed fields are
+    /// left unset
+    pub fn new(values: &[u32]) -> Self {
+        let mut hints = SizeHints::default();
+
+        /* the pre-ICCCM version of the property stops before base size */
+        if values.len() < 15 {
+            return hints;
+        }
+
+        let flags = values[0];
+
+        let size = |w: u32, h: u32| {
+            let clamp = |v: u32| v.min(i16::MAX as u32) as u16;
+            Size { w: clamp(w), h: clamp(h) }
+        };
+
+        let positive = |size: Size| {
+            if size.w > 0 || size.h > 0 { Some(size) } else { None }
+        };
+
+        if flags & P_MIN_SIZE != 0 {
+            hints.min = positive(size(values[5], values[6]));
+        }
+
+        if flags & P_MAX_SIZE != 0 {
+            hints.max = positive(size(values[7], values[8]));
+        }
+
+        if flags & P_RESIZE_INC != 0 {
+            hints.increment = positive(size(values[9], values[10]));
+        }
+
+        if flags & P_ASPECT != 0 && values[12] > 0 && values[14] > 0 {
+            hints.min_aspect = Some((values[11], values[12]));
+            hints.max_aspect = Some((values[13], values[14]));
+        }
+
+        if flags & P_BASE_SIZE != 0 && values.len() >= 17 {
+            hints.base = Some(size(values[15], values[16]));
+        }
+
+        if flags & P_WIN_GRAVITY != 0 && values.len() >= 18 {
+            hints.gravity = match values[17] {
+                2 => x::Gravity::North,
+                3 => x::Gravity::NorthEast,
+                4 => x::Gravity::West,
+                5 => x::Gravity::Center,
+                6 => x::Gravity::East,
+                7 => x::Gravity::SouthWest,
+                8 => x::Gravity::South,
+                9 => x::Gravity::SouthEast,
+                10 => x::Gravity::Static,
+                _ => x::Gravity::NorthWest,
+            };
+        }
+
+        hints
+    }
+
+    /// Whether the client allows a single size only
+    pub fn fixed(&self) -> bool {
+        match (self.min, self.max) {
+            (Some(min), Some(max)) => min == max,
+            _ => false,
+        }
+    }
+
+    /// Largest size that fits in available and satisfies the hints, as in
+    /// ICCCM 4.1.2.3. The minimum size wins over available
+    pub fn constrain(&self, available: Size) -> Size {
+        /* base and minimum size stand in for each other */
+        let zero = Size { w: 0, h: 0 };
+        let base = self.base.or(self.min).unwrap_or(zero);
+        let min = self.min.or(self.base).unwrap_or(zero);
+
+        let mut w = available.w as u32;
+        let mut h = available.h as u32;
+
+        /* aspect ratios apply to the size above base, unless base is
+         * only standing in for the minimum */
+        let base_is_min = self.base.is_none();
+
+        if !base_is_min {
+            w = w.saturating_sub(base.w as u32);
+            h = h.saturating_sub(base.h as u32);
+        }
+
+        if let (Some((min_x, min_y)), Some((max_x, max_y))) = (self.min_aspect, self.max_aspect) {
+            /* w / h must stay within min_x / min_y and max_x / max_y */
+            if max_x > 0 && (w as u64) * (max_y as u64) > (h as u64) * (max_x as u64) {
+                w = ((h as u64) * (max_x as u64) / (max_y as u64)) as u32;
+            } else if min_x > 0 && (w as u64) * (min_y as u64) < (h as u64) * (min_x as u64) {
+                h = ((w as u64) * (min_y as u64) / (min_x as u64)) as u32;
+            }
+        }
+
+        if base_is_min {
+            w = w.saturating_sub(base.w as u32);
+            h = h.saturating_sub(base.h as u32);
+        }
+
+        if let Some(increment) = self.increment {
+            if increment.w > 0 {
+                w -= w % increment.w as u32;
+            }
+
+            if increment.h > 0 {
+                h -= h % increment.h as u32;
+            }
+        }
+
+        w = (w + base.w as u32).max(min.w as u32);
+        h = (h + base.h as u32).max(min.h as u32);
+
+        if let Some(max) = self.max {
+            if max.w > 0 {
+                w = w.min(max.w as u32);
+            }
+
+            if max.h > 0 {
+                h = h.min(max.h as u32);
+            }
+        }
+
+        Size {
+            w: w.min(i16::MAX as u32) as u16,
+            h: h.min(i16::MAX as u32) as u16,
+        }
+    }
+}
+
+#[cfg(test)]
+mod size_hints_tests {
+    use super::*;
+
+    fn hints(flags: u32, fields: &[(usize, u32)]) -> SizeHints {
+        let mut values = [0; 18];
+        values[0] = flags;
+
+        for (i, v) in fields {
+            values[*i] = *v;
+        }
+
+        SizeHints::new(&values)
+    }
+
+    #[test]
+    fn test_parse() {
+        let h = hints(P_MIN_SIZE | P_RESIZE_INC | P_BASE_SIZE | P_WIN_GRAVITY, &[
+            (5, 10), (6, 20), (9, 7), (10, 14), (15, 2), (16, 4), (17, 5),
+        ]);
+
+        assert_eq!(h.min, Some(Size { w: 10, h: 20 }));
+        assert_eq!(h.max, None);
+        assert_eq!(h.increment, Some(Size { w: 7, h: 14 }));
+        assert_eq!(h.base, Some(Size { w: 2, h: 4 }));
+        assert_eq!(h.gravity, x::Gravity::Center);
+
+        /* fields without their flag are ignored */
+        let h = hints(0, &[(5, 10), (6, 20)]);
+        assert_eq!(h, SizeHints::default());
+
+        /* too short to be a size hints property */
+        assert_eq!(SizeHints::new(&[P_MIN_SIZE, 0, 0]), SizeHints::default());
+    }
+
+    #[test]
+    fn test_increments() {
+        /* a terminal: 7x14 cells with a 2px border */
+        let h = hints(P_RESIZE_INC | P_BASE_SIZE, &[(9, 7), (10, 14), (15, 4), (16, 4)]);
+
+        assert_eq!(h.constrain(Size { w: 805, h: 600 }), Size { w: 802, h: 592 });
+        assert_eq!(h.constrain(Size { w: 802, h: 592 }), Size { w: 802, h: 592 });
+    }
+
+    #[test]
+    fn test_min_max() {
+        let h = hints(P_MIN_SIZE | P_MAX_SIZE, &[(5, 100), (6, 100), (7, 400), (8, 300)]);
+
+        assert_eq!(h.constrain(Size { w: 50, h: 50 }), Size { w: 100, h: 100 });
+        assert_eq!(h.constrain(Size { w: 800, h: 600 }), Size { w: 400, h: 300 });
+        assert_eq!(h.constrain(Size { w: 200, h: 200 }), Size { w: 200, h: 200 });
+        assert!(!h.fixed());
+
+        let h = hints(P_MIN_SIZE | P_MAX_SIZE, &[(5, 300), (6, 200), (7, 300), (8, 200)]);
+        assert!(h.fixed());
+        assert_eq!(h.constrain(Size { w: 1000, h: 1000 }), Size { w: 300, h: 200 });
+    }
+
+    #[test]
+    fn test_aspect() {
+        /* exactly 16:9 */
+        let h = hints(P_ASPECT, &[(11, 16), (12, 9), (13, 16), (14, 9)]);
+
+        assert_eq!(h.constrain(Size { w: 1920, h: 1200 }), Size { w: 1920, h: 1080 });
+        assert_eq!(h.constrain(Size { w: 2000, h: 1080 }), Size { w: 1920, h: 1080 });
+    }
+}
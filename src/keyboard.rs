@@ -46,7 +46,7 @@ impl<'a> Iterator for KeycodeIterator<'a> {
             /* next keysym in keycode */
             self.index += 1;
 
-            if &self.keysyms[i] == &self.target {
+            if self.keysyms[i] == self.target {
                 /* seek to next keycode */
                 self.index = match self.index % self.per {
                     0 => self.index,
@@ -80,21 +80,21 @@ impl KeyMap {
 
         Ok(KeyMap {
             min: min.into(),
-            keymap: keymap,
-            modmap: modmap,
+            keymap,
+            modmap,
         })
     }
 
-    fn keycodes(&self, keysym: Keysym) -> KeycodeIterator {
+    fn keycodes(&self, keysym: Keysym) -> KeycodeIterator<'_> {
         let per = self.keymap.keysyms_per_keycode() as usize;
         let keysyms = self.keymap.keysyms();
 
         KeycodeIterator {
             min: self.min as usize,
-            per: per,
+            per,
             index: 0,
             target: keysym,
-            keysyms: keysyms,
+            keysyms,
         }
     }
 
@@ -151,11 +151,11 @@ impl Keyboard {
         let scroll_lock = keymap.mask(keysym::Scroll_Lock)?;
 
         Ok(Keyboard {
-            conn: conn,
-            keymap: keymap,
-            num_lock: num_lock,
-            caps_lock: caps_lock,
-            scroll_lock: scroll_lock,
+            conn,
+            keymap,
+            num_lock,
+            caps_lock,
+            scroll_lock,
             map: HashMap::new(),
         })
     }
@@ -166,13 +166,13 @@ impl Keyboard {
             keycode: Keycode)
         -> xcb::VoidCookieChecked
     {
-        let m = unsafe { x::ModMask::from_bits_unchecked(modifiers.bits()) };
+        let m = x::ModMask::from_bits_truncate(modifiers.bits());
 
         self.conn.send_request_checked(&x::GrabKey {
             owner_events: true,
             grab_window: self.conn.root(),
             modifiers: m,
-            key: keycode as u8,
+            key: keycode,
             pointer_mode: x::GrabMode::Async,
             keyboard_mode: x::GrabMode::Async,
         })
@@ -242,10 +242,10 @@ impl Keyboard {
             .or_else(|| self.map.get(&(root, KeyModifier::ANY, code, press)))
     }
 
-    pub fn press(&self, root: x::Window, mask: x::KeyButMask, code: Keycode, press: bool) {
+    pub fn press(&self, root: x::Window, mask: x::KeyButMask, code: Keycode, press: bool, time: x::Timestamp) {
         let key = self.get(root, mask, code, press);
         if let Some(x) = key {
-            self.conn.produce(Event::Binding { key: x.clone() });
+            self.conn.produce(Event::Binding { key: x.clone(), time });
         }
     }
 }
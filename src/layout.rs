@@ -1,4 +1,4 @@
-use crate::rect::Rect;
+use crate::rect::{Rect, Size, Cut};
 
 #[derive(Debug)]
 pub enum Cell {
@@ -7,11 +7,55 @@ pub enum Cell {
     Focus(Rect),
 }
 
+/// Description of a child being arranged by a layout
+#[derive(Debug, Clone)]
+pub struct Child {
+    /// The child is the layout's focused child. Only the cell of a focused
+    /// child on the manager's focus path actually receives input focus
+    pub focused: bool,
+    /// Current geometry of the child
+    pub size: Rect,
+    /// Size limits of the child, from the client's size hints
+    pub min: Option<Size>,
+    pub max: Option<Size>,
+    /// Relative share of the scope, 1.0 by default
+    pub weight: f32,
+    /// Floating children are not expected to be tiled
+    pub floating: bool,
+    /// WM_CLASS class of a window child
+    pub class: Option<String>,
+}
+
+impl Child {
+    pub fn new(size: Rect) -> Self {
+        Child {
+            focused: false,
+            size,
+            min: None,
+            max: None,
+            weight: 1.0,
+            floating: false,
+            class: None,
+        }
+    }
+}
+
+/// Layouts place every child in a single arrange_all() call. arrange() is
+/// an adapter for placing one child at a time.
 pub trait Layout {
-    fn arrange(&mut self, index: usize, count: usize, focus: bool, scope: Rect) -> Cell;
+    /// Arrange the child at index out of count children
+    fn arrange(&mut self, index: usize, count: usize, focus: bool, scope: Rect) -> Cell {
+        let mut children = vec![Child::new(scope); count];
+        children[index].focused = focus;
+
+        self.arrange_all(&children, scope).swap_remove(index)
+    }
+
+    /// Arrange every child, returning one cell per child in the same order
+    fn arrange_all(&mut self, children: &[Child], scope: Rect) -> Vec<Cell>;
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct Monacle { }
 
 impl Monacle {
@@ -21,11 +65,519 @@ impl Monacle {
 }
 
 impl Layout for Monacle {
-    fn arrange(&mut self, _: usize, _: usize, focus: bool, scope: Rect) -> Cell {
-        if focus {
-            Cell::Focus(scope)
+    /// Show the focused child over the whole scope. With no focused tiled
+    /// child the last tiled child is shown, so the scope is never left
+    /// empty. Floating children keep their size
+    fn arrange_all(&mut self, children: &[Child], scope: Rect) -> Vec<Cell> {
+        let shown = children.iter().position(|c| c.focused && !c.floating)
+            .or_else(|| children.iter().rposition(|c| !c.floating));
+
+        children.iter().enumerate().map(|(i, child)| {
+            if child.floating && child.focused {
+                Cell::Focus(child.size)
+            } else if child.floating {
+                Cell::Show(child.size)
+            } else if Some(i) != shown {
+                Cell::Hide
+            } else if child.focused {
+                Cell::Focus(scope)
+            } else {
+                Cell::Show(scope)
+            }
+        }).collect()
+    }
+}
+
+/// Side of the scope the master area is placed on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Orientation {
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+/* bounds of the master ratio, so neither area ever collapses */
+const MIN_RATIO: f32 = 0.05;
+const MAX_RATIO: f32 = 0.95;
+
+/// Master/stack tiling. The first `masters` children share the master area,
+/// taking `ratio` of the scope, and the rest share the remaining stack area.
+#[derive(Debug, Clone)]
+pub struct Tile {
+    masters: usize,
+    ratio: f32,
+    orientation: Orientation,
+}
+
+impl Tile {
+    pub fn new(masters: usize, ratio: f32, orientation: Orientation) -> Self {
+        Tile {
+            masters,
+            ratio: ratio.clamp(MIN_RATIO, MAX_RATIO),
+            orientation,
+        }
+    }
+
+    pub fn masters(&self) -> usize {
+        self.masters
+    }
+
+    pub fn set_masters(&mut self, masters: usize) {
+        self.masters = masters;
+    }
+
+    pub fn ratio(&self) -> f32 {
+        self.ratio
+    }
+
+    pub fn set_ratio(&mut self, ratio: f32) {
+        self.ratio = ratio.clamp(MIN_RATIO, MAX_RATIO);
+    }
+
+    pub fn orientation(&self) -> Orientation {
+        self.orientation
+    }
+
+    pub fn set_orientation(&mut self, orientation: Orientation) {
+        self.orientation = orientation;
+    }
+
+    /// Split scope into the (master, stack) areas
+    fn areas(&self, scope: Rect) -> (Rect, Rect) {
+        let cut = |length: u16| (length as f32 * self.ratio).round() as u16;
+
+        match self.orientation {
+            Orientation::Left => {
+                scope.cut(Cut::Vertical(cut(scope.w)))
+            }
+            Orientation::Right => {
+                let (stack, master) = scope.cut(Cut::Vertical(scope.w - cut(scope.w)));
+                (master, stack)
+            }
+            Orientation::Top => {
+                scope.cut(Cut::Horizontal(cut(scope.h)))
+            }
+            Orientation::Bottom => {
+                let (stack, master) = scope.cut(Cut::Horizontal(scope.h - cut(scope.h)));
+                (master, stack)
+            }
+        }
+    }
+
+    /// Divide an area among children, perpendicular to the master cut
+    fn divide(&self, area: Rect, children: &[&Child]) -> Vec<Rect> {
+        let vertical = matches!(self.orientation, Orientation::Left | Orientation::Right);
+
+        let (start, length) = if vertical {
+            (area.y, area.h)
+        } else {
+            (area.x, area.w)
+        };
+
+        /* only the size along the divided axis can be honored */
+        let along = |size: Option<Size>| {
+            size.map(|s| if vertical { s.h } else { s.w }).filter(|l| *l > 0)
+        };
+
+        let limits: Vec<_> = children.iter().map(|child| {
+            (child.weight, along(child.min).unwrap_or(0), along(child.max).unwrap_or(u16::MAX))
+        }).collect();
+
+        let mut offset = start;
+
+        shares(length, &limits).into_iter().map(|share| {
+            let rect = if vertical {
+                Rect::new(area.x, offset, area.w, share)
+            } else {
+                Rect::new(offset, area.y, share, area.h)
+            };
+
+            offset += share as i16;
+            rect
+        }).collect()
+    }
+}
+
+/// Share length out in proportion to the (weight, min, max) of each child.
+/// Shares stay within their bounds while the length allows, and add up to
+/// length unless every child is held at its max
+fn shares(length: u16, limits: &[(f32, u16, u16)]) -> Vec<u16> {
+    let count = limits.len();
+
+    let weight = |i: usize| {
+        let weight = limits[i].0;
+        if weight.is_finite() && weight > 0.0 { weight } else { 0.0 }
+    };
+
+    /* minimums that cannot all be met are ignored */
+    let fit = limits.iter().map(|l| l.1 as u32).sum::<u32>() <= length as u32;
+    let min = |i: usize| if fit { limits[i].1 as f32 } else { 0.0 };
+    let max = |i: usize| limits[i].2 as f32;
+
+    let mut exact = vec![0.0; count];
+    let mut fixed = vec![false; count];
+
+    /* children out of bounds are held at the bound they crossed and the
+     * rest share what is left, until every share is within bounds */
+    loop {
+        let free: Vec<usize> = (0..count).filter(|i| !fixed[*i]).collect();
+
+        if free.is_empty() {
+            break;
+        }
+
+        let held: f32 = (0..count).filter(|i| fixed[*i]).map(|i| exact[i]).sum();
+        let left = (length as f32 - held).max(0.0);
+        let total: f32 = free.iter().map(|i| weight(*i)).sum();
+
+        for &i in free.iter() {
+            exact[i] = if total > 0.0 {
+                left * weight(i) / total
+            } else {
+                left / free.len() as f32
+            };
+        }
+
+        /* hold only the side that is further out, as holding it changes
+         * what is left for the other */
+        let under: f32 = free.iter().map(|i| (min(*i) - exact[*i]).max(0.0)).sum();
+        let over: f32 = free.iter().map(|i| (exact[*i] - max(*i)).max(0.0)).sum();
+
+        if under == 0.0 && over == 0.0 {
+            break;
+        }
+
+        for &i in free.iter() {
+            if under >= over && exact[i] < min(i) {
+                exact[i] = min(i);
+                fixed[i] = true;
+            } else if over > under && exact[i] > max(i) {
+                exact[i] = max(i);
+                fixed[i] = true;
+            }
+        }
+    }
+
+    /* round the running total so the shares add up exactly */
+    let mut sum = 0.0;
+    let mut end = 0;
+
+    exact.into_iter().map(|share| {
+        let start = end;
+
+        sum += share;
+        end = (sum.round() as u16).clamp(start, length);
+        end - start
+    }).collect()
+}
+
+impl Layout for Tile {
+    /// Tile every non-floating child, weighting the split of the master and
+    /// stack areas. Floating children keep their size
+    fn arrange_all(&mut self, children: &[Child], scope: Rect) -> Vec<Cell> {
+        let tiled: Vec<&Child> = children.iter().filter(|c| !c.floating).collect();
+
+        let masters = self.masters.min(tiled.len());
+        let stack = tiled.len() - masters;
+
+        let mut rects = if masters == 0 || stack == 0 {
+            self.divide(scope, &tiled)
         } else {
-            Cell::Hide
+            let (master, rest) = self.areas(scope);
+            let mut rects = self.divide(master, &tiled[..masters]);
+
+            rects.extend(self.divide(rest, &tiled[masters..]));
+            rects
+        }.into_iter();
+
+        children.iter().map(|child| {
+            let rect = if child.floating {
+                child.size
+            } else {
+                rects.next().expect("Tile: one rect per tiled child")
+            };
+
+            /* more children than pixels leaves some cells empty */
+            if !child.floating && (rect.w == 0 || rect.h == 0) {
+                Cell::Hide
+            } else if child.focused {
+                Cell::Focus(rect)
+            } else {
+                Cell::Show(rect)
+            }
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod monacle_tests {
+    use super::*;
+
+    fn shown(cells: &[Cell]) -> Vec<usize> {
+        cells.iter().enumerate().filter_map(|(i, c)| {
+            match c {
+                Cell::Hide => None,
+                _ => Some(i),
+            }
+        }).collect()
+    }
+
+    #[test]
+    fn test_monacle_focus() {
+        let scope = Rect::new(0, 0, 100, 60);
+        let mut children = vec![Child::new(scope); 3];
+        children[1].focused = true;
+
+        let cells = Monacle::new().arrange_all(&children, scope);
+        assert_eq!(shown(&cells), vec![1]);
+        assert!(matches!(cells[1], Cell::Focus(rect) if rect == scope));
+    }
+
+    #[test]
+    fn test_monacle_unfocused() {
+        let scope = Rect::new(0, 0, 100, 60);
+        let mut children = vec![Child::new(scope); 3];
+        children[2].floating = true;
+
+        let cells = Monacle::new().arrange_all(&children, scope);
+        assert_eq!(shown(&cells), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_monacle_no_focus() {
+        let scope = Rect::new(0, 0, 100, 60);
+        let children = vec![Child::new(scope); 3];
+
+        /* nothing is focused: the last child is shown rather than none */
+        let cells = Monacle::new().arrange_all(&children, scope);
+        assert_eq!(shown(&cells), vec![2]);
+        assert!(matches!(cells[2], Cell::Show(rect) if rect == scope));
+    }
+
+    #[test]
+    fn test_monacle_adapter() {
+        let scope = Rect::new(0, 0, 100, 60);
+
+        assert!(matches!(Monacle::new().arrange(0, 2, true, scope), Cell::Focus(_)));
+        assert!(matches!(Monacle::new().arrange(0, 2, false, scope), Cell::Hide));
+    }
+}
+
+#[cfg(test)]
+mod tile_tests {
+    use super::*;
+
+    fn area(rect: &Rect) -> u32 {
+        rect.w as u32 * rect.h as u32
+    }
+
+    fn overlap(a: &Rect, b: &Rect) -> bool {
+        a.left() < b.right() && b.left() < a.right()
+            && a.top() < b.bottom() && b.top() < a.bottom()
+    }
+
+    fn cells(tile: &mut Tile, count: usize, scope: Rect) -> Vec<Rect> {
+        (0..count).map(|i| {
+            match tile.arrange(i, count, false, scope) {
+                Cell::Show(rect) => rect,
+                cell => panic!("unexpected cell {:?}", cell),
+            }
+        }).collect()
+    }
+
+    fn rects(tile: &mut Tile, children: &[Child], scope: Rect) -> Vec<Rect> {
+        tile.arrange_all(children, scope).into_iter().map(|c| {
+            match c {
+                Cell::Show(rect) => rect,
+                cell => panic!("unexpected cell {:?}", cell),
+            }
+        }).collect()
+    }
+
+    /// Cells that are shown, skipping hidden ones
+    fn shown(tile: &mut Tile, count: usize, scope: Rect) -> Vec<Rect> {
+        (0..count).filter_map(|i| {
+            match tile.arrange(i, count, false, scope) {
+                Cell::Show(rect) => Some(rect),
+                Cell::Hide => None,
+                cell => panic!("unexpected cell {:?}", cell),
+            }
+        }).collect()
+    }
+
+    fn assert_tiles(tile: &mut Tile, count: usize, scope: Rect) {
+        let rects = shown(tile, count, scope);
+
+        for (i, a) in rects.iter().enumerate() {
+            assert!(a.w > 0 && a.h > 0, "empty cell {} ({} children, {:?})", a, count, tile);
+            assert!(a.left() >= scope.left() && a.right() <= scope.right(), "{} outside {}", a, scope);
+            assert!(a.top() >= scope.top() && a.bottom() <= scope.bottom(), "{} outside {}", a, scope);
+
+            for b in rects[i + 1..].iter() {
+                assert!(!overlap(a, b), "{} overlaps {} ({} children, {:?})", a, b, count, tile);
+            }
+        }
+
+        assert_eq!(rects.iter().map(area).sum::<u32>(), area(&scope), "{} children, {:?}", count, tile);
+    }
+
+    #[test]
+    fn test_tile_exact() {
+        let orientations = [Orientation::Left, Orientation::Right, Orientation::Top, Orientation::Bottom];
+        let scopes = [Rect::new(0, 0, 1920, 1080), Rect::new(13, 7, 101, 37)];
+
+        for scope in scopes {
+            for orientation in orientations {
+                for masters in 0..4 {
+                    for ratio in [0.0, 0.3, 0.55, 1.0] {
+                        let mut tile = Tile::new(masters, ratio, orientation);
+
+                        for count in 1..=12 {
+                            assert_tiles(&mut tile, count, scope);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_tile_ratio_bounds() {
+        let scope = Rect::new(0, 0, 100, 60);
+
+        let mut tile = Tile::new(1, 0.0, Orientation::Left);
+        assert_eq!(tile.ratio(), MIN_RATIO);
+        assert_eq!(cells(&mut tile, 2, scope), vec![
+            Rect::new(0, 0, 5, 60),
+            Rect::new(5, 0, 95, 60),
+        ]);
+
+        tile.set_ratio(1.0);
+        assert_eq!(tile.ratio(), MAX_RATIO);
+        assert_eq!(cells(&mut tile, 2, scope), vec![
+            Rect::new(0, 0, 95, 60),
+            Rect::new(95, 0, 5, 60),
+        ]);
+    }
+
+    #[test]
+    fn test_tile_crowded() {
+        let mut tile = Tile::new(1, 0.5, Orientation::Left);
+        let scope = Rect::new(0, 0, 10, 4);
+
+        /* the stack is 4px high: only 4 of its 11 children fit */
+        assert_eq!(shown(&mut tile, 12, scope).len(), 5);
+        assert_tiles(&mut tile, 12, scope);
+
+        for count in 1..=40 {
+            assert_tiles(&mut tile, count, Rect::new(3, 2, 3, 2));
+        }
+    }
+
+    #[test]
+    fn test_tile_weight() {
+        let mut tile = Tile::new(1, 0.5, Orientation::Left);
+        let scope = Rect::new(0, 0, 100, 60);
+
+        let mut children = vec![Child::new(scope); 3];
+        children[1].weight = 2.0;
+
+        assert_eq!(rects(&mut tile, &children, scope), vec![
+            Rect::new(0, 0, 50, 60),
+            Rect::new(50, 0, 50, 40),
+            Rect::new(50, 40, 50, 20),
+        ]);
+
+        /* a weight of zero gets no space while others can take it */
+        children[1].weight = 0.0;
+
+        let cells = tile.arrange_all(&children, scope);
+        assert!(matches!(cells[1], Cell::Hide));
+        assert!(matches!(cells[2], Cell::Show(rect) if rect == Rect::new(50, 0, 50, 60)));
+    }
+
+    #[test]
+    fn test_tile_min_max() {
+        let mut tile = Tile::new(0, 0.5, Orientation::Top);
+        let scope = Rect::new(0, 0, 100, 60);
+
+        let mut children = vec![Child::new(scope); 4];
+        children[0].max = Some(Size { w: 10, h: 0 });
+        children[1].min = Some(Size { w: 40, h: 0 });
+
+        /* 10 + 40, and the other two share the remaining 50 */
+        assert_eq!(rects(&mut tile, &children, scope), vec![
+            Rect::new(0, 0, 10, 60),
+            Rect::new(10, 0, 40, 60),
+            Rect::new(50, 0, 25, 60),
+            Rect::new(75, 0, 25, 60),
+        ]);
+
+        /* minimums that do not fit are ignored */
+        children[2].min = Some(Size { w: 80, h: 0 });
+        assert_tiles(&mut tile, 4, scope);
+        assert_eq!(rects(&mut tile, &children, scope)[0], Rect::new(0, 0, 10, 60));
+
+        /* with every child held at its max the rest of the scope is empty */
+        for child in children.iter_mut() {
+            child.min = None;
+            child.max = Some(Size { w: 10, h: 0 });
+        }
+
+        assert_eq!(rects(&mut tile, &children, scope)[3], Rect::new(30, 0, 10, 60));
+    }
+
+    #[test]
+    fn test_tile_left() {
+        let mut tile = Tile::new(1, 0.5, Orientation::Left);
+        let scope = Rect::new(0, 0, 100, 60);
+
+        assert_eq!(cells(&mut tile, 1, scope), vec![scope]);
+        assert_eq!(cells(&mut tile, 3, scope), vec![
+            Rect::new(0, 0, 50, 60),
+            Rect::new(50, 0, 50, 30),
+            Rect::new(50, 30, 50, 30),
+        ]);
+    }
+
+    #[test]
+    fn test_tile_bottom() {
+        let mut tile = Tile::new(2, 0.25, Orientation::Bottom);
+        let scope = Rect::new(0, 0, 100, 60);
+
+        assert_eq!(cells(&mut tile, 3, scope), vec![
+            Rect::new(0, 45, 50, 15),
+            Rect::new(50, 45, 50, 15),
+            Rect::new(0, 0, 100, 45),
+        ]);
+    }
+
+    #[test]
+    fn test_tile_floating() {
+        let mut tile = Tile::new(1, 0.5, Orientation::Left);
+        let scope = Rect::new(0, 0, 100, 60);
+
+        let mut float = Child::new(Rect::new(10, 10, 20, 20));
+        float.floating = true;
+
+        let children = [Child::new(scope), float, Child::new(scope)];
+
+        assert_eq!(rects(&mut tile, &children, scope), vec![
+            Rect::new(0, 0, 50, 60),
+            Rect::new(10, 10, 20, 20),
+            Rect::new(50, 0, 50, 60),
+        ]);
+    }
+
+    #[test]
+    fn test_tile_focus() {
+        let mut tile = Tile::new(1, 0.5, Orientation::Right);
+        let scope = Rect::new(0, 0, 100, 60);
+
+        match tile.arrange(0, 2, true, scope) {
+            Cell::Focus(rect) => assert_eq!(rect, Rect::new(50, 0, 50, 60)),
+            cell => panic!("unexpected cell {:?}", cell),
         }
     }
 }
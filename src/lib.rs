@@ -1,11 +1,17 @@
 pub mod rect;
+pub mod arena;
 pub mod tree;
 pub mod error;
 pub mod process;
+pub mod poll;
+pub mod atoms;
 pub mod manager;
 pub mod monitor;
 pub mod container;
+pub mod workspace;
 pub mod window;
+pub mod ewmh;
+pub mod hints;
 pub mod layout;
 pub mod keysym;
 pub mod keyboard;
@@ -1,26 +1,96 @@
+use std::time::Duration;
+
+use xcb::x;
+
 use wm::keysym;
 use wm::error::Error;
 use wm::manager::{Manager, Event};
 use wm::keyboard::{Key, KeyModifier, KeyPress};
+use wm::layout::{Tile, Orientation};
+use wm::rect::Direction;
 
 use wm::process;
 
-fn handle_key(key: Key) {
+const WORKSPACES: &[&str] = &["1", "2", "3", "4"];
+
+fn handle_key(mgr: &mut Manager, key: Key, time: x::Timestamp) -> Result<(), Error> {
     match key {
         Key { keysym: keysym::i, .. } => process::spawn(&["firefox"]),
         Key { keysym: keysym::Return, .. } => process::spawn(&["st"]),
         Key { keysym: keysym::q, .. } => { std::process::exit(0) },
+        Key { keysym: keysym::c, .. } => {
+            if let Some(id) = mgr.root.focused() {
+                mgr.close(id, Some(time))?;
+            }
+        }
+        Key { keysym: keysym::n, mask, .. } => {
+            if mask.contains(KeyModifier::SHIFT) {
+                mgr.root.restore_last()?;
+            } else if let Some(id) = mgr.root.focused() {
+                mgr.root.minimize(id)?;
+            }
+        }
+        Key { keysym: keysym::o, .. } => {
+            /* send the current workspace to the monitor on the right */
+            let current = mgr.root.workspaces().current();
+            let monitor = current
+                .and_then(|id| mgr.root.workspaces().get(id))
+                .and_then(|ws| ws.monitor())
+                .and_then(|monitor| mgr.monitors.neighbour(monitor, Direction::Right));
+
+            if let (Some(id), Some(monitor)) = (current, monitor) {
+                mgr.move_workspace(id, monitor)?;
+            }
+        }
+        Key { keysym: k @ keysym::KEY_1..=keysym::KEY_4, mask, .. } => {
+            let name = WORKSPACES[(k - keysym::KEY_1) as usize];
+
+            if let Some(id) = mgr.root.workspaces().find(name) {
+                if mask.contains(KeyModifier::SHIFT) {
+                    mgr.move_to_workspace(id)?;
+                } else {
+                    mgr.switch_workspace(id)?;
+                }
+            }
+        }
         _ => {
-            panic!("unknown key??? {:?}", key);
+            eprintln!("unbound key: {:?}", key);
         },
     }
+
+    Ok(())
 }
 
 fn handle(mgr: &mut Manager, e: Event) -> Result<(), Error> {
-    match dbg!(e) {
-        Event::Binding { key } => handle_key(key),
+    match e {
+        Event::Binding { key, time } => handle_key(mgr, key, time)?,
         Event::WindowShow { window: id } => {
-            mgr.root[id].as_window_mut().show()?;
+            /* dialogs of windows on hidden workspaces stay hidden with them */
+            let hidden = mgr.root.get(id)
+                .and_then(|node| node.as_window_ref().workspace())
+                .and_then(|ws| mgr.root.workspaces().get(ws))
+                .is_some_and(|ws| !ws.visible());
+
+            /* the window may be gone by the time we see the event */
+            if let Some(node) = mgr.root.get_mut(id) {
+                if !hidden {
+                    node.as_window_mut().show()?;
+                }
+
+                mgr.root.focus(id, None)?;
+            }
+        }
+        Event::ActivateRequest { window: id, time, .. } => {
+            /* bring the window's workspace along */
+            let workspace = mgr.root.get(id).and_then(|node| node.as_window_ref().workspace());
+
+            if let Some(workspace) = workspace {
+                mgr.switch_workspace(workspace)?;
+            }
+
+            /* minimized windows come back first */
+            mgr.root.restore(id)?;
+            mgr.root.focus(id, time)?;
         }
         _ => {},
     }
@@ -31,9 +101,8 @@ fn handle(mgr: &mut Manager, e: Event) -> Result<(), Error> {
 fn run(mut mgr: Manager) {
     loop {
         match mgr.next() {
-            Ok(Some(x)) => handle(&mut mgr, x).expect("failed to handle event"),
+            Ok(x) => handle(&mut mgr, x).expect("failed to handle event"),
             Err(e) => eprintln!("error: {}", e),
-            _ => {},
         }
     }
 }
@@ -60,5 +129,41 @@ fn main() {
         press: KeyPress::Press,
     }).expect("bind key failed");
 
+    mgr.keyboard.bind(Key {
+        mask: KeyModifier::MOD4,
+        keysym: keysym::c,
+        press: KeyPress::Press,
+    }).expect("bind key failed");
+
+    /* clients that ignore WM_DELETE_WINDOW are killed after a while */
+    mgr.set_kill_timeout(Some(Duration::from_secs(5)));
+
+    for mask in [KeyModifier::MOD4, KeyModifier::MOD4 | KeyModifier::SHIFT] {
+        mgr.keyboard.bind(Key {
+            mask,
+            keysym: keysym::n,
+            press: KeyPress::Press,
+        }).expect("bind key failed");
+    }
+
+    mgr.keyboard.bind(Key {
+        mask: KeyModifier::MOD4,
+        keysym: keysym::o,
+        press: KeyPress::Press,
+    }).expect("bind key failed");
+
+    for (i, name) in WORKSPACES.iter().enumerate() {
+        mgr.create_workspace(name, Tile::new(1, 0.5, Orientation::Left))
+            .expect("failed to create workspace");
+
+        for mask in [KeyModifier::MOD4, KeyModifier::MOD4 | KeyModifier::SHIFT] {
+            mgr.keyboard.bind(Key {
+                mask,
+                keysym: keysym::KEY_1 + i as u32,
+                press: KeyPress::Press,
+            }).expect("bind key failed");
+        }
+    }
+
     run(mgr)
 }
@@ -1,17 +1,26 @@
-use std::sync::mpsc;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{mpsc, Arc, Mutex};
 use std::mem;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::time::{Duration, Instant};
 
 use xcb::x;
 
+use crate::atoms::Atoms;
 use crate::error::Error;
+use crate::poll::{Poll, TimerId, Token};
 use crate::monitor::{Monitors, MonitorId};
 use crate::keyboard::{Keyboard, Key};
-use crate::container::{Container, ContainerId};
+use crate::container::{Container, ContainerId, ContainerNode};
+use crate::ewmh::{ActivateSource, Strut};
+use crate::workspace::WorkspaceId;
+use crate::layout::Layout;
+use crate::rect::Rect;
 
 /// Required xcb extensions
-static REQUIRED: &'static [xcb::Extension] = &[xcb::Extension::RandR];
+static REQUIRED: &[xcb::Extension] = &[xcb::Extension::RandR];
 /// Optional xcb extensions
-static OPTIONAL: &'static [xcb::Extension] = &[];
+static OPTIONAL: &[xcb::Extension] = &[];
 
 
 #[derive(Debug)]
@@ -23,7 +32,16 @@ pub enum Event {
     WindowCreate { window: ContainerId, x: i16, y: i16, width: u16, height: u16 },
     WindowResize { window: ContainerId, x: i16, y: i16, width: u16, height: u16 },
     WindowShow { window: ContainerId },
-    Binding { key: Key },
+    WindowHide { window: ContainerId },
+    WindowDestroy { window: ContainerId },
+    FocusChange { old: Option<ContainerId>, new: Option<ContainerId> },
+    WorkspaceSwitch { old: Option<WorkspaceId>, new: WorkspaceId },
+    ActivateRequest { window: ContainerId, source: ActivateSource, time: Option<x::Timestamp> },
+    WindowFullscreen { window: ContainerId, fullscreen: bool },
+    WindowTitle { window: ContainerId, title: String },
+    Binding { key: Key, time: x::Timestamp },
+    Timer { id: TimerId },
+    Readable { token: Token },
 }
 
 /// This atrocity is to force Rust to allow us to pass around a cloned
@@ -60,6 +78,9 @@ pub struct Connection {
     handle: Handle,
     screen: usize,
     root: x::Window,
+    atoms: Atoms,
+    /* events read off the socket while waiting for a particular one */
+    deferred: Arc<Mutex<VecDeque<xcb::Result<xcb::Event>>>>,
     events: mpsc::Sender<Event>,
 }
 
@@ -68,6 +89,8 @@ impl Clone for Connection {
         Self {
             screen: self.screen,
             root: self.root,
+            atoms: self.atoms,
+            deferred: self.deferred.clone(),
             handle: self.handle.clone(),
             events: self.events.clone(),
         }
@@ -75,16 +98,19 @@ impl Clone for Connection {
 }
 
 impl Connection {
-    fn new(xcb: &xcb::Connection, screen: usize, sender: &mpsc::Sender<Event>) -> Self {
+    fn new(xcb: &xcb::Connection, screen: usize, sender: &mpsc::Sender<Event>) -> Result<Self, Error> {
         let setup = xcb.get_setup();
         let root = setup.roots().nth(screen).unwrap().root();
+        let atoms = Atoms::intern_all(xcb)?;
 
-        Self {
-            screen: screen,
-            root: root,
+        Ok(Self {
+            screen,
+            root,
+            atoms,
+            deferred: Arc::new(Mutex::new(VecDeque::new())),
             handle: Handle::new(xcb),
             events: sender.clone(),
-        }
+        })
     }
 
     #[inline]
@@ -97,7 +123,38 @@ impl Connection {
         self.screen
     }
 
-    pub fn raw<'a>(&'a self) -> &'a xcb::Connection {
+    #[inline]
+    pub fn atoms(&self) -> &Atoms {
+        &self.atoms
+    }
+
+    /// Block until window reports a change of property, and return the
+    /// server time of the change. Events read in the meantime are kept for
+    /// the event loop, in order
+    pub(crate) fn wait_for_property(&self, window: x::Window, property: x::Atom) -> Result<x::Timestamp, Error> {
+        self.handle.xcb.flush()?;
+
+        loop {
+            match self.handle.xcb.wait_for_event() {
+                Ok(xcb::Event::X(x::Event::PropertyNotify(e))) if e.window() == window && e.atom() == property => {
+                    return Ok(e.time());
+                }
+                Err(xcb::Error::Connection(e)) => {
+                    return Err(e.into());
+                }
+                event => {
+                    self.deferred.lock().expect("deferred events poisoned").push_back(event);
+                }
+            }
+        }
+    }
+
+    /// Oldest event kept by wait_for_property, if any
+    fn deferred(&self) -> Option<xcb::Result<xcb::Event>> {
+        self.deferred.lock().expect("deferred events poisoned").pop_front()
+    }
+
+    pub fn raw(&self) -> &xcb::Connection {
         &self.handle.xcb
     }
 
@@ -153,6 +210,13 @@ pub struct Manager {
     raw: xcb::Connection, // lifetime only, use conn instead. See Handle comments
     conn: Connection,
     events: mpsc::Receiver<Event>,
+    poll: Poll,
+    /* struts the work areas were last computed with */
+    struts: Vec<Strut>,
+    /* how long a closed window may linger before its client is killed */
+    kill_timeout: Option<Duration>,
+    /* windows to kill when their timer expires */
+    kills: HashMap<TimerId, ContainerId>,
     pub monitors: Monitors,
     pub keyboard: Keyboard,
     pub root: Container,
@@ -163,22 +227,52 @@ impl Manager {
         match event {
             xcb::Event::RandR(xcb::randr::Event::ScreenChangeNotify(_)) => {
                 self.monitors.update()?;
+                self.bind()?;
             }
             xcb::Event::X(xcb::x::Event::KeyPress(ref e)) => {
-                self.keyboard.press(e.root(), e.state(), e.detail() as x::Keycode, true);
+                self.keyboard.press(e.root(), e.state(), e.detail() as x::Keycode, true, e.time());
             }
             xcb::Event::X(xcb::x::Event::KeyRelease(ref e)) => {
-                self.keyboard.press(e.root(), e.state(), e.detail() as x::Keycode, false);
+                self.keyboard.press(e.root(), e.state(), e.detail() as x::Keycode, false, e.time());
             }
             xcb::Event::X(xcb::x::Event::CreateNotify(ref e)) => {
-                self.root.create(e);
+                self.root.create(e)?;
             }
             xcb::Event::X(xcb::x::Event::ConfigureRequest(ref e)) => {
                 self.root.configure(e);
             }
             xcb::Event::X(xcb::x::Event::MapRequest(ref e)) => {
-                self.root.map(e);
-
+                self.root.map(e)?;
+                self.update_struts()?;
+            }
+            xcb::Event::X(xcb::x::Event::UnmapNotify(ref e)) => {
+                self.root.unmap(e)?;
+                self.update_struts()?;
+            }
+            xcb::Event::X(xcb::x::Event::DestroyNotify(ref e)) => {
+                self.root.destroy(e)?;
+                self.update_struts()?;
+            }
+            xcb::Event::X(xcb::x::Event::FocusIn(ref e)) => {
+                self.root.focus_in(e)?;
+            }
+            xcb::Event::X(xcb::x::Event::PropertyNotify(ref e)) => {
+                self.root.property(e)?;
+                self.update_struts()?;
+            }
+            xcb::Event::X(xcb::x::Event::ClientMessage(ref e)) if e.r#type() == self.conn.atoms.net_close_window => {
+                /* pagers closing windows get the same treatment as bindings */
+                if let Some(id) = self.root.from_window(e.window()) {
+                    let time = match e.data() {
+                        x::ClientMessageData::Data32(data) if data[0] != x::CURRENT_TIME => Some(data[0]),
+                        _ => None,
+                    };
+
+                    self.close(id, time)?;
+                }
+            }
+            xcb::Event::X(xcb::x::Event::ClientMessage(ref e)) => {
+                self.root.client_message(e)?;
             }
             _ => {
             }
@@ -189,6 +283,11 @@ impl Manager {
 }
 
 impl Manager {
+    /// Connection shared by the manager's components
+    pub fn conn(&self) -> &Connection {
+        &self.conn
+    }
+
     /// Connect the manager to an X server
     pub fn connect(name: Option<&str>, screenopt: Option<usize>) -> Result<Self, Error> {
 
@@ -197,7 +296,7 @@ impl Manager {
         let screen = screenopt.unwrap_or(main as usize);
         let (tx, rx) = mpsc::channel();
 
-        let conn = Connection::new(&raw, screen, &tx);
+        let conn = Connection::new(&raw, screen, &tx)?;
 
         /* substructure redirect -- the core "window manager" flag.
          * only one process can set this attribute at a time, and it
@@ -216,45 +315,360 @@ impl Manager {
 
         let monitors = Monitors::new(conn.clone())?;
         let keyboard = Keyboard::new(conn.clone())?;
-        let container = Container::new(conn.clone())?;
+        let mut container = Container::new(conn.clone())?;
+
+        /* windows mapped before we took over never send a MapRequest */
+        container.scan()?;
 
         let mgr = Manager {
-            raw: raw,
-            conn: conn,
+            raw,
+            conn,
             events: rx,
-            monitors: monitors,
-            keyboard: keyboard,
+            poll: Poll::new(),
+            struts: vec![],
+            kill_timeout: None,
+            kills: HashMap::new(),
+            monitors,
+            keyboard,
             root: container,
         };
 
         Ok(mgr)
     }
 
+    /// Create a workspace arranged by layout. It is shown straight away if
+    /// a monitor has nothing else to show
+    pub fn create_workspace<L: Layout + 'static>(&mut self, name: &str, layout: L) -> Result<WorkspaceId, Error> {
+        let id = self.root.create_workspace(name, layout)?;
+        self.bind()?;
+
+        Ok(id)
+    }
+
+    /// Rename a workspace. Returns false if id does not exist
+    pub fn rename_workspace(&mut self, id: WorkspaceId, name: &str) -> bool {
+        self.root.rename_workspace(id, name)
+    }
+
+    /// Show a workspace on its monitor, in place of the one shown there
+    pub fn switch_workspace(&mut self, id: WorkspaceId) -> Result<(), Error> {
+        let scope = self.scope(id);
+        self.root.switch_workspace(id, scope)
+    }
+
+    /// Move the focused container to a workspace
+    pub fn move_to_workspace(&mut self, id: WorkspaceId) -> Result<(), Error> {
+        match self.root.focused() {
+            Some(focused) => self.root.move_to_workspace(focused, id),
+            None => Ok(()),
+        }
+    }
+
+    /// Move a workspace to another monitor, which becomes its home. It is
+    /// shown there, and its old monitor shows another of its workspaces
+    pub fn move_workspace(&mut self, id: WorkspaceId, monitor: MonitorId) -> Result<(), Error> {
+        let (name, screen) = match self.monitors.get(monitor) {
+            Some(mon) => (mon.name.clone(), mon.size),
+            None => return Ok(()),
+        };
+
+        if self.root.workspaces().get(id).is_none() {
+            return Ok(());
+        }
+
+        self.root.hide_workspace(id)?;
+        self.root.workspaces_mut().set_monitor(id, Some(monitor), Some(screen));
+        self.root.workspaces_mut().set_home(id, Some(name));
+
+        self.switch_workspace(id)?;
+        self.bind()
+    }
+
+    /// Area a workspace is arranged in: the work area of its monitor, or
+    /// the whole root window when it has none
+    fn scope(&self, id: WorkspaceId) -> Rect {
+        let monitor = self.root.workspaces().get(id)
+            .and_then(|ws| ws.monitor())
+            .and_then(|monitor| self.monitors.get(monitor));
+
+        match monitor {
+            Some(monitor) => self.workarea(monitor.size),
+            None => self.screen(),
+        }
+    }
+
+    /// Size of the root window
+    fn screen(&self) -> Rect {
+        let root = self.root.root();
+
+        match self.root.get(root) {
+            Some(node) => node.as_window_ref().size(),
+            None => Rect::new(0, 0, 0, 0),
+        }
+    }
+
+    /// Part of a monitor that no dock reserved
+    fn workarea(&self, monitor: Rect) -> Rect {
+        let screen = self.screen();
+
+        self.struts.iter()
+            .fold(monitor, |area, strut| strut.reserve(area, screen))
+    }
+
+    /// Re-arrange every monitor when docks reserve different space
+    fn update_struts(&mut self) -> Result<(), Error> {
+        let struts: Vec<_> = self.root.struts().collect();
+
+        if struts != self.struts {
+            self.struts = struts;
+            self.bind()?;
+        }
+
+        Ok(())
+    }
+
+    /// Place every workspace on a connected monitor and make sure every
+    /// monitor shows one. Workspaces return to their home monitor whenever
+    /// it is connected, and migrate off monitors that disconnect.
+    fn bind(&mut self) -> Result<(), Error> {
+        let monitors: Vec<_> = self.monitors.iter()
+            .map(|(id, mon)| (id, mon.name.clone()))
+            .collect();
+
+        let home = |name: Option<&str>| {
+            monitors.iter()
+                .find(|(_, other)| Some(other.as_str()) == name)
+                .map(|(id, _)| *id)
+        };
+
+        let workspaces: Vec<_> = self.root.workspaces().iter()
+            .map(|(id, ws)| (id, ws.monitor(), home(ws.home())))
+            .collect();
+
+        /* monitors that no placed workspace is on yet */
+        let mut empty: Vec<_> = monitors.iter()
+            .map(|(id, _)| *id)
+            .filter(|id| !workspaces.iter().any(|(_, monitor, home)| home.or(*monitor) == Some(*id)))
+            .collect();
+
+        let fallback = self.root.workspaces().current()
+            .and_then(|ws| self.root.workspaces().get(ws))
+            .and_then(|ws| ws.monitor())
+            .filter(|monitor| self.monitors.get(*monitor).is_some())
+            .or(monitors.first().map(|(id, _)| *id));
+
+        for (id, monitor, home) in workspaces.into_iter() {
+            let connected = monitor.filter(|monitor| self.monitors.get(*monitor).is_some());
+
+            /* new workspaces spread over the monitors with nothing on them */
+            let target = match (home, connected, monitor) {
+                (Some(home), _, _) => Some(home),
+                (None, Some(monitor), _) => Some(monitor),
+                (None, None, None) if !empty.is_empty() => Some(empty.remove(0)),
+                _ => fallback,
+            };
+
+            if target != monitor {
+                self.root.hide_workspace(id)?;
+            }
+
+            /* monitors keep their id when resized, so always refresh the size */
+            let screen = target.and_then(|target| self.monitors.get(target)).map(|mon| mon.size);
+            self.root.workspaces_mut().set_monitor(id, target, screen);
+
+            /* the first monitor a workspace lands on is its home */
+            let named = self.root.workspaces().get(id).is_some_and(|ws| ws.home().is_some());
+            let name = target.and_then(|target| self.monitors.get(target)).map(|mon| mon.name.clone());
+
+            if !named && name.is_some() {
+                self.root.workspaces_mut().set_home(id, name);
+            }
+        }
+
+        for (monitor, _) in monitors.iter() {
+            let on: Vec<_> = self.root.workspaces().iter()
+                .filter(|(_, ws)| ws.monitor() == Some(*monitor))
+                .map(|(id, ws)| (id, ws.visible()))
+                .collect();
+
+            /* re-arrange what is shown, the monitor may have been resized */
+            let shown = on.iter().find(|(_, visible)| *visible).or(on.first());
+
+            if let Some((id, _)) = shown {
+                let scope = self.scope(*id);
+                self.root.show_workspace(*id, scope)?;
+            }
+        }
+
+        /* the focus leaves workspaces that are no longer shown */
+        let visible = |id: &WorkspaceId| {
+            self.root.workspaces().get(*id).is_some_and(|ws| ws.visible())
+        };
+
+        let current = self.root.workspaces().current();
+
+        if !current.is_some_and(|id| visible(&id)) {
+            let shown = self.root.workspaces().iter()
+                .map(|(id, _)| id)
+                .find(|id| visible(id))
+                .or(self.root.workspaces().iter().map(|(id, _)| id).next());
+
+            if let Some(id) = shown {
+                self.switch_workspace(id)?;
+            }
+        }
+
+        /* every workspace's usable area, or the screen's without any */
+        let mut areas: Vec<_> = self.root.workspaces().iter()
+            .map(|(id, _)| self.scope(id))
+            .collect();
+
+        if areas.is_empty() {
+            areas.push(self.screen());
+        }
+
+        self.root.ewmh().set_workarea(&areas)?;
+
+        Ok(())
+    }
+
+    /// Kill clients that have not closed their window this long after
+    /// close() asked them to. None, the default, never kills them
+    pub fn set_kill_timeout(&mut self, timeout: Option<Duration>) {
+        self.kill_timeout = timeout;
+    }
+
+    /// Ask a window to close, with the time of the event asking for it if
+    /// there is one. Clients that do not support WM_DELETE_WINDOW are
+    /// killed straight away, the others once the kill timeout passes
+    pub fn close(&mut self, id: ContainerId, time: Option<x::Timestamp>) -> Result<(), Error> {
+        match self.root.get(id) {
+            Some(ContainerNode::Window(_)) if id != self.root.root() => {}
+            _ => return Ok(()),
+        }
+
+        let time = self.root.time(time)?;
+        let window = self.root.get(id).expect("checked above").as_window_ref();
 
-    pub fn next(&mut self) -> Result<Option<Event>, Error> {
-        match self.events.try_recv() {
-            Ok(event) => {
-                return Ok(Some(event))
-            },
-            Err(mpsc::TryRecvError::Disconnected) => {
-                panic!("mpsc::Sender disconnected!");
-            },
-            Err(mpsc::TryRecvError::Empty) => {
+        if !window.close(time)? {
+            return window.kill();
+        }
+
+        if let Some(timeout) = self.kill_timeout {
+            let timer = self.timer(timeout);
+            self.kills.insert(timer, id);
+        }
+
+        Ok(())
+    }
+
+    /// Kill the client of a window, unless it is already gone
+    pub fn kill(&mut self, id: ContainerId) -> Result<(), Error> {
+        match self.root.get(id) {
+            Some(ContainerNode::Window(window)) if id != self.root.root() => window.kill(),
+            _ => Ok(()),
+        }
+    }
+
+    /// Produce Event::Timer once, after the given delay
+    pub fn timer(&mut self, after: Duration) -> TimerId {
+        self.poll.timer(Instant::now() + after, None)
+    }
+
+    /// Produce Event::Timer every interval, until cancelled
+    pub fn repeat(&mut self, interval: Duration) -> TimerId {
+        self.poll.timer(Instant::now() + interval, Some(interval))
+    }
+
+    /// Cancel a pending timer. Returns false if it already expired
+    pub fn cancel(&mut self, id: TimerId) -> bool {
+        self.poll.cancel(id)
+    }
+
+    /// Produce Event::Readable whenever fd has data to read. The caller
+    /// keeps ownership of fd, and must unwatch() it before closing it
+    pub fn watch(&mut self, fd: RawFd) -> Token {
+        self.poll.watch(fd)
+    }
+
+    /// Stop watching a file descriptor
+    pub fn unwatch(&mut self, token: Token) -> bool {
+        self.poll.unwatch(token)
+    }
+
+    /// Block until the next event
+    #[allow(clippy::should_implement_trait)] // fallible and blocking, not an Iterator
+    pub fn next(&mut self) -> Result<Event, Error> {
+        loop {
+            if let Some(event) = self.wait(None)? {
+                return Ok(event);
             }
         }
+    }
+
+    /// Block until the next event, or return None once timeout elapses
+    pub fn next_timeout(&mut self, timeout: Duration) -> Result<Option<Event>, Error> {
+        self.wait(Some(Instant::now() + timeout))
+    }
+
+    fn wait(&mut self, deadline: Option<Instant>) -> Result<Option<Event>, Error> {
+        loop {
+            match self.events.try_recv() {
+                Ok(event) => {
+                    return Ok(Some(event))
+                },
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    panic!("mpsc::Sender disconnected!");
+                },
+                Err(mpsc::TryRecvError::Empty) => {
+                }
+            }
+
+            /* neither do events put aside while waiting for another */
+            if let Some(event) = self.conn.deferred() {
+                self.handle(event?)?;
+                continue;
+            }
+
+            /* events already read off the socket never wake up poll() */
+            if let Some(event) = self.conn.raw().poll_for_queued_event()? {
+                self.handle(event)?;
+                continue;
+            }
+
+            let now = Instant::now();
+            let expired = self.poll.expire(now);
+
+            if !expired.is_empty() {
+                for id in expired {
+                    /* timers of our own are never seen by the user */
+                    match self.kills.remove(&id) {
+                        Some(window) => self.kill(window)?,
+                        None => self.conn.produce(Event::Timer { id }),
+                    }
+                }
+
+                continue;
+            }
+
+            if deadline.is_some_and(|at| at <= now) {
+                return Ok(None);
+            }
+
+            self.conn.raw().flush()?;
+
+            let timeout = self.poll.timeout(now, deadline);
+            let (xcb, readable) = self.poll.poll(self.conn.raw().as_raw_fd(), timeout)?;
+
+            for token in readable {
+                self.conn.produce(Event::Readable { token });
+            }
 
-        let event = self.conn.handle.xcb.wait_for_event()?;
-        self.handle(event)?;
-
-        match self.events.try_recv() {
-            Ok(event) => {
-                return Ok(Some(event))
-            },
-            Err(mpsc::TryRecvError::Disconnected) => {
-                panic!("mpsc::Sender disconnected!");
-            },
-            Err(mpsc::TryRecvError::Empty) => {
-                Ok(None)
+            if xcb {
+                /* reads the socket. may still produce nothing */
+                if let Some(event) = self.conn.raw().poll_for_event()? {
+                    self.handle(event)?;
+                }
             }
         }
     }
@@ -2,17 +2,16 @@ use std::collections::HashMap;
 
 use xcb::x;
 use xcb::randr::{self, Output};
-use slab::Slab;
 
-use crate::rect::Rect;
+use crate::arena::{Arena, Key};
+use crate::rect::{Rect, Direction};
 use crate::error::Error;
 use crate::manager::{Connection, Event};
 
-#[repr(transparent)]
+/// Handle to a connected monitor. Ids of disconnected monitors never refer
+/// to a later connection
 #[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
-pub struct MonitorId {
-    id: usize,
-}
+pub struct MonitorId(Key);
 
 pub struct Monitor {
     pub root: x::Window,
@@ -22,7 +21,7 @@ pub struct Monitor {
 
 pub struct Monitors {
     conn: Connection,
-    monitors: Slab<Monitor>,
+    monitors: Arena<Monitor>,
 }
 
 impl Monitors {
@@ -38,8 +37,8 @@ impl Monitors {
         })?;
 
         let mut mon = Monitors {
-            conn: conn,
-            monitors: Slab::new(),
+            conn,
+            monitors: Arena::new(),
         };
 
         mon.update()?;
@@ -51,6 +50,29 @@ impl Monitors {
     pub fn update(&mut self) -> Result<(), Error> {
         self.update_root(self.conn.root())
     }
+
+    pub fn get(&self, id: MonitorId) -> Option<&Monitor> {
+        self.monitors.get(id.0)
+    }
+
+    pub fn get_mut(&mut self, id: MonitorId) -> Option<&mut Monitor> {
+        self.monitors.get_mut(id.0)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (MonitorId, &Monitor)> {
+        self.monitors.iter().map(|(k, v)| (MonitorId(k), v))
+    }
+
+    /// Nearest monitor in a direction from id
+    pub fn neighbour(&self, id: MonitorId, direction: Direction) -> Option<MonitorId> {
+        let from = self.get(id)?.size;
+
+        let candidates = self.monitors.iter()
+            .filter(|(k, _)| *k != id.0)
+            .map(|(k, v)| (k.index(), v.size));
+
+        from.nearest(direction, candidates).map(|i| MonitorId(self.monitors.key(i)))
+    }
 }
 
 impl Monitors {
@@ -61,11 +83,11 @@ impl Monitors {
         let name = &mon.name;
 
         for (k, v) in self.monitors.iter_mut() {
-            if &v.root == &root && &v.name == name {
+            if v.root == root && &v.name == name {
 
-                id = MonitorId { id: k };
+                id = MonitorId(k);
 
-                if &v.size != &mon.size {
+                if v.size != mon.size {
                     self.conn.produce(Event::MonitorTransform {
                         monitor: id,
                         x: mon.size.x,
@@ -80,7 +102,7 @@ impl Monitors {
             }
         }
 
-        id = MonitorId { id: self.monitors.vacant_key() };
+        id = MonitorId(self.monitors.vacant_key());
 
         self.conn.produce(Event::MonitorConnect {
             monitor: id,
@@ -120,8 +142,6 @@ impl Monitors {
 
     /// Refresh monitor state for a given root window
     fn update_root(&mut self, root: x::Window) -> Result<(), Error> {
-        self.monitors.shrink_to_fit();
-
         let cookie = self.conn.send_request(&randr::GetMonitors {
             window: root,
             get_active: true,
@@ -131,21 +151,15 @@ impl Monitors {
 
         let reply = self.conn.wait_for_reply(cookie)?;
 
-        let mut disconnected = vec![false; self.monitors.capacity()];
-        for (k, _) in self.monitors.iter() {
-            disconnected[k] = true;
-        }
+        let mut disconnected: Vec<_> = self.monitors.iter().map(|(k, _)| MonitorId(k)).collect();
 
-        for (_, info) in reply.monitors().enumerate() {
+        for info in reply.monitors() {
             let mut connected = false;
 
             for output in info.outputs() {
-                match outputs.get(output) {
-                    Some(randr::Connection::Connected) => {
-                        connected = true;
-                        break;
-                    },
-                    _ => { }
+                if let Some(randr::Connection::Connected) = outputs.get(output) {
+                    connected = true;
+                    break;
                 }
             }
 
@@ -161,14 +175,12 @@ impl Monitors {
             let name = String::from(reply.name().to_utf8());
 
             let id = self.add(root, Monitor {
-                root: root,
-                name: name,
-                size: size,
+                root,
+                name,
+                size,
             });
 
-            if id.id < disconnected.len() {
-                disconnected[id.id] = false;
-            }
+            disconnected.retain(|k| *k != id);
 
             if info.primary() {
                 self.conn.produce(Event::MonitorPrimary {
@@ -177,30 +189,14 @@ impl Monitors {
             }
         }
 
-        for (i, dc) in disconnected.into_iter().enumerate() {
-            if dc {
-                self.monitors.remove(i);
+        for id in disconnected {
+            self.monitors.remove(id.0);
 
-                self.conn.produce(Event::MonitorDisconnect {
-                    monitor: MonitorId { id: i },
-                });
-            }
+            self.conn.produce(Event::MonitorDisconnect {
+                monitor: id,
+            });
         }
 
         Ok(())
     }
 }
-
-impl std::ops::Index<MonitorId> for Monitors {
-    type Output = Monitor;
-
-    fn index(&self, id: MonitorId) -> &Self::Output {
-        &self.monitors[id.id]
-    }
-}
-
-impl std::ops::IndexMut<MonitorId> for Monitors {
-    fn index_mut(&mut self, id: MonitorId) -> &mut Self::Output {
-        &mut self.monitors[id.id]
-    }
-}
@@ -0,0 +1,226 @@
+use std::os::unix::io::RawFd;
+use std::time::{Duration, Instant};
+
+use crate::arena::{Arena, Key};
+use crate::error::Error;
+
+/// Handle to a timer. A stale handle never refers to a later timer
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct TimerId(Key);
+
+/// Handle to a watched descriptor
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct Token(Key);
+
+struct Timer {
+    deadline: Instant,
+    interval: Option<Duration>,
+}
+
+/// Timers and external file descriptors multiplexed with the X connection
+pub struct Poll {
+    timers: Arena<Timer>,
+    fds: Arena<RawFd>,
+}
+
+impl Default for Poll {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Poll {
+    pub fn new() -> Self {
+        Poll {
+            timers: Arena::new(),
+            fds: Arena::new(),
+        }
+    }
+
+    /// Add a timer firing once at deadline, or every interval after it
+    pub fn timer(&mut self, deadline: Instant, interval: Option<Duration>) -> TimerId {
+        TimerId(self.timers.insert(Timer {
+            deadline,
+            interval,
+        }))
+    }
+
+    /// Cancel a timer. Returns false if it already expired or never existed
+    pub fn cancel(&mut self, id: TimerId) -> bool {
+        self.timers.remove(id.0).is_some()
+    }
+
+    /// Watch fd for readability
+    pub fn watch(&mut self, fd: RawFd) -> Token {
+        Token(self.fds.insert(fd))
+    }
+
+    /// Stop watching a file descriptor. Returns false if it was not watched
+    pub fn unwatch(&mut self, token: Token) -> bool {
+        self.fds.remove(token.0).is_some()
+    }
+
+    /// Collect every timer due at now, ordered by deadline. One-shot timers
+    /// are removed, repeating timers are rescheduled.
+    pub fn expire(&mut self, now: Instant) -> Vec<TimerId> {
+        let mut due: Vec<_> = self.timers.iter()
+            .filter(|(_, timer)| timer.deadline <= now)
+            .map(|(key, timer)| (timer.deadline, key.index()))
+            .collect();
+
+        due.sort();
+
+        let expired: Vec<_> = due.iter()
+            .map(|(_, id)| TimerId(self.timers.key(*id)))
+            .collect();
+
+        for (_, id) in due.into_iter() {
+            let timer = &mut self.timers[id];
+
+            match timer.interval {
+                Some(interval) => {
+                    /* skip missed intervals rather than firing a burst */
+                    while timer.deadline <= now {
+                        timer.deadline += interval.max(Duration::from_millis(1));
+                    }
+                }
+                None => {
+                    self.timers.remove(self.timers.key(id));
+                }
+            }
+        }
+
+        expired
+    }
+
+    /// Time left until the next timer or deadline, whichever is sooner.
+    /// None means wait forever.
+    pub fn timeout(&self, now: Instant, deadline: Option<Instant>) -> Option<Duration> {
+        self.timers.iter()
+            .map(|(_, timer)| timer.deadline)
+            .chain(deadline)
+            .min()
+            .map(|at| at.saturating_duration_since(now))
+    }
+
+    /// Block until xcb or a watched descriptor is readable, or timeout
+    /// elapses. Returns whether xcb is readable, and the readable tokens.
+    pub fn poll(&self, xcb: RawFd, timeout: Option<Duration>) -> Result<(bool, Vec<Token>), Error> {
+        let mut pollfds = Vec::with_capacity(self.fds.len() + 1);
+        let mut tokens = Vec::with_capacity(self.fds.len());
+
+        pollfds.push(libc::pollfd { fd: xcb, events: libc::POLLIN, revents: 0 });
+
+        for (key, fd) in self.fds.iter() {
+            pollfds.push(libc::pollfd { fd: *fd, events: libc::POLLIN, revents: 0 });
+            tokens.push(Token(key));
+        }
+
+        let ms = match timeout {
+            /* round up, so we never wake just before a deadline */
+            Some(t) => t.as_nanos().div_ceil(1_000_000).min(libc::c_int::MAX as u128) as libc::c_int,
+            None => -1,
+        };
+
+        let n = unsafe {
+            libc::poll(pollfds.as_mut_ptr(), pollfds.len() as libc::nfds_t, ms)
+        };
+
+        if n < 0 {
+            let err = std::io::Error::last_os_error();
+
+            return match err.kind() {
+                std::io::ErrorKind::Interrupted => Ok((false, vec![])),
+                _ => Err(err.into()),
+            };
+        }
+
+        let ready = |pfd: &libc::pollfd| {
+            pfd.revents & (libc::POLLIN | libc::POLLHUP | libc::POLLERR) != 0
+        };
+
+        let readable = std::iter::zip(pollfds[1..].iter(), tokens)
+            .filter(|(pfd, _)| ready(pfd))
+            .map(|(_, token)| token)
+            .collect();
+
+        Ok((ready(&pollfds[0]), readable))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expire_order() {
+        let mut poll = Poll::new();
+        let now = Instant::now();
+
+        let late = poll.timer(now + Duration::from_millis(20), None);
+        let early = poll.timer(now + Duration::from_millis(10), None);
+        let never = poll.timer(now + Duration::from_secs(60), None);
+
+        assert!(poll.expire(now).is_empty());
+        assert_eq!(poll.expire(now + Duration::from_millis(30)), vec![early, late]);
+        assert!(poll.expire(now + Duration::from_millis(30)).is_empty());
+        assert!(poll.cancel(never));
+        assert!(!poll.cancel(early));
+    }
+
+    #[test]
+    fn test_stale() {
+        let mut poll = Poll::new();
+        let now = Instant::now();
+
+        /* a new timer reuses the slot of a cancelled one */
+        let old = poll.timer(now, None);
+        assert!(poll.cancel(old));
+        let new = poll.timer(now, None);
+
+        assert_ne!(old, new);
+        assert!(!poll.cancel(old));
+        assert_eq!(poll.expire(now), vec![new]);
+
+        /* and the slot of an expired one */
+        let newer = poll.timer(now, None);
+        assert!(!poll.cancel(new));
+        assert!(poll.cancel(newer));
+
+        let old = poll.watch(0);
+        assert!(poll.unwatch(old));
+        let new = poll.watch(0);
+
+        assert!(!poll.unwatch(old));
+        assert!(poll.unwatch(new));
+    }
+
+    #[test]
+    fn test_repeat() {
+        let mut poll = Poll::new();
+        let now = Instant::now();
+        let every = Duration::from_millis(10);
+
+        let id = poll.timer(now + every, Some(every));
+
+        assert_eq!(poll.expire(now + every), vec![id]);
+        assert_eq!(poll.timeout(now + every, None), Some(every));
+
+        /* missed intervals fire once */
+        assert_eq!(poll.expire(now + every * 5), vec![id]);
+        assert_eq!(poll.timeout(now + every * 5, None), Some(every));
+    }
+
+    #[test]
+    fn test_timeout() {
+        let mut poll = Poll::new();
+        let now = Instant::now();
+
+        assert_eq!(poll.timeout(now, None), None);
+        assert_eq!(poll.timeout(now, Some(now + Duration::from_secs(1))), Some(Duration::from_secs(1)));
+
+        poll.timer(now + Duration::from_millis(5), None);
+        assert_eq!(poll.timeout(now, Some(now + Duration::from_secs(1))), Some(Duration::from_millis(5)));
+        assert_eq!(poll.timeout(now + Duration::from_secs(1), None), Some(Duration::ZERO));
+    }
+}
@@ -23,7 +23,7 @@ pub fn spawn<T>(args: &[T])
         /* double fork so init handles zombies */
         if let Ok(Fork::Child) = fork::fork() {
             unsafe {
-                libc::execvp(cs[0], (&cs[..]).as_ptr() as *const *const i8);
+                libc::execvp(cs[0], cs.as_ptr() as *const *const i8);
             }
 
             eprintln!("failed to spawn process");
@@ -17,6 +17,12 @@ pub struct Rect {
     pub h: u16,
 }
 
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Size {
+    pub w: u16,
+    pub h: u16,
+}
+
 pub enum Corner {
     TopLeft,
     TopRight,
@@ -24,6 +30,14 @@ pub enum Corner {
     BottomRight,
 }
 
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Direction {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
 pub enum Cut {
     Horizontal(u16),
     Vertical(u16),
@@ -66,10 +80,10 @@ impl Rect {
         }
 
         Rect {
-            x: x,
-            y: y,
-            w: w,
-            h: h,
+            x,
+            y,
+            w,
+            h,
         }
     }
 
@@ -101,6 +115,15 @@ impl Rect {
         }
     }
 
+    /// Same size, moved to the middle of other. Sides that do not fit in
+    /// other line up with its top or left edge
+    pub fn centered(&self, other: &Rect) -> Rect {
+        let dx = (other.w as i16 - self.w as i16).max(0) / 2;
+        let dy = (other.h as i16 - self.h as i16).max(0) / 2;
+
+        Rect::new(other.x + dx, other.y + dy, self.w, self.h)
+    }
+
     pub fn corner(&self, at: Corner) -> Point {
         match at {
             Corner::TopLeft => Point {
@@ -147,6 +170,47 @@ impl Rect {
         }
     }
 
+    /// Find the candidate nearest to this rect in the given direction.
+    /// Candidates must have their center strictly in that direction.
+    /// Candidates overlapping this rect's extent perpendicular to the
+    /// direction win over those that do not, then the nearest center wins.
+    /// Remaining ties go to the smallest key, so overlapping windows always
+    /// resolve the same way.
+    pub fn nearest<K, I>(&self, direction: Direction, candidates: I) -> Option<K>
+    where
+        K: Ord,
+        I: IntoIterator<Item = (K, Rect)>,
+    {
+        let from = self.center();
+        let topleft = self.corner(Corner::TopLeft);
+        let bottomright = self.corner(Corner::BottomRight);
+
+        candidates.into_iter().filter_map(|(key, rect)| {
+            let to = rect.center();
+
+            let dx = to.x as i32 - from.x as i32;
+            let dy = to.y as i32 - from.y as i32;
+
+            let rows = rect.top() < bottomright.y && topleft.y < rect.bottom();
+            let cols = rect.left() < bottomright.x && topleft.x < rect.right();
+
+            let (along, across, overlap) = match direction {
+                Direction::Left => (-dx, dy.abs(), rows),
+                Direction::Right => (dx, dy.abs(), rows),
+                Direction::Up => (-dy, dx.abs(), cols),
+                Direction::Down => (dy, dx.abs(), cols),
+            };
+
+            if along > 0 {
+                Some(((!overlap, along + across, across), key))
+            } else {
+                None
+            }
+        })
+        .min()
+        .map(|(_, key)| key)
+    }
+
     pub fn split<'a>(&'a self, at: Split) -> SplitIterator<'a> {
         match at {
             Split::Horizontal(n) => SplitIterator::Horizontal(HorizontalSplit::new(self, n)),
@@ -197,17 +261,17 @@ impl<'a> HorizontalSplit<'a> {
         }
 
         let height = if count > 0 {
-            1 + ((rect.h - 1) / count as u16)
+            rect.h.div_ceil(count as u16)
         } else {
             0
         };
 
         HorizontalSplit {
-            rect: rect,
-            count: count,
+            rect,
+            count,
             index: 0,
             bottom: rect.y,
-            height: height,
+            height,
         }
     }
 }
@@ -219,17 +283,17 @@ impl<'a> VerticalSplit<'a> {
         }
 
         let width = if count > 0 {
-            1 + ((rect.w - 1) / count as u16)
+            rect.w.div_ceil(count as u16)
         } else {
             0
         };
 
         VerticalSplit {
-            rect: rect,
-            count: count,
+            rect,
+            count,
             index: 0,
             right: rect.x,
-            width: width,
+            width,
         }
     }
 }
@@ -248,6 +312,8 @@ impl<'a> Iterator for HorizontalSplit<'a> {
             if y >= self.rect.bottom() {
                 y = self.rect.bottom();
                 height = 0;
+            } else if y + height as i16 > self.rect.bottom() {
+                height = (self.rect.bottom() - y) as u16;
             }
 
             self.bottom = y + height as i16;
@@ -255,7 +321,7 @@ impl<'a> Iterator for HorizontalSplit<'a> {
 
             Some(Rect {
                 x: self.rect.x,
-                y: y,
+                y,
                 w: self.rect.w,
                 h: height,
             })
@@ -288,13 +354,15 @@ impl<'a> Iterator for VerticalSplit<'a> {
             if x >= self.rect.right() {
                 x = self.rect.right();
                 width = 0;
+            } else if x + width as i16 > self.rect.right() {
+                width = (self.rect.right() - x) as u16;
             }
 
             self.right = x + width as i16;
             self.index += 1;
 
             Some(Rect {
-                x: x,
+                x,
                 y: self.rect.y,
                 w: width,
                 h: self.rect.h,
@@ -372,6 +440,40 @@ mod split_tests {
         assert!(row.next().is_none());
     }
 
+    #[test]
+    fn test_uneven_split() {
+        let rect = Rect::new(0, 0, 5, 5);
+
+        let mut col = rect.split(Split::Vertical(4));
+        assert_eq!(col.next().unwrap(), Rect::new(0, 0, 2, 5));
+        assert_eq!(col.next().unwrap(), Rect::new(2, 0, 2, 5));
+        assert_eq!(col.next().unwrap(), Rect::new(4, 0, 1, 5));
+        assert_eq!(col.next().unwrap(), Rect::new(5, 0, 0, 5));
+        assert!(col.next().is_none());
+
+        let mut row = rect.split(Split::Horizontal(4));
+        assert_eq!(row.next().unwrap(), Rect::new(0, 0, 5, 2));
+        assert_eq!(row.next().unwrap(), Rect::new(0, 2, 5, 2));
+        assert_eq!(row.next().unwrap(), Rect::new(0, 4, 5, 1));
+        assert_eq!(row.next().unwrap(), Rect::new(0, 5, 5, 0));
+        assert!(row.next().is_none());
+    }
+
+    #[test]
+    fn test_zero_size_split() {
+        let rect = Rect::new(3, 4, 0, 0);
+
+        let mut col = rect.split(Split::Vertical(2));
+        assert_eq!(col.next().unwrap(), Rect::new(3, 4, 0, 0));
+        assert_eq!(col.next().unwrap(), Rect::new(3, 4, 0, 0));
+        assert!(col.next().is_none());
+
+        let mut row = rect.split(Split::Horizontal(2));
+        assert_eq!(row.next().unwrap(), Rect::new(3, 4, 0, 0));
+        assert_eq!(row.next().unwrap(), Rect::new(3, 4, 0, 0));
+        assert!(row.next().is_none());
+    }
+
     #[test]
     fn test_single_split() {
         let rect = Rect::new(0, 0, 5, 7);
@@ -402,7 +504,7 @@ mod split_tests {
             assert_eq!(col.next().unwrap(), Rect::new(0, i, 5, 1));
         }
 
-        for i in 7i16..i16::MAX {
+        for _ in 7i16..i16::MAX {
             assert_eq!(col.next().unwrap(), Rect::new(0, 7, 5, 0));
         }
 
@@ -419,10 +521,99 @@ mod split_tests {
             assert_eq!(col.next().unwrap(), Rect::new(i, 0, 1, 5));
         }
 
-        for i in 7i16..i16::MAX {
+        for _ in 7i16..i16::MAX {
             assert_eq!(col.next().unwrap(), Rect::new(7, 0, 0, 5));
         }
 
         assert!(col.next().is_none());
     }
 }
+
+#[cfg(test)]
+mod nearest_tests {
+    use super::*;
+
+    /* +---+---+
+     * |   | 1 |
+     * | 0 +---+
+     * |   | 2 |
+     * +---+---+ */
+    fn tiled() -> Vec<(usize, Rect)> {
+        vec![
+            (0, Rect::new(0, 0, 50, 100)),
+            (1, Rect::new(50, 0, 50, 50)),
+            (2, Rect::new(50, 50, 50, 50)),
+        ]
+    }
+
+    fn nearest(from: usize, direction: Direction, rects: &[(usize, Rect)]) -> Option<usize> {
+        let rect = rects[from].1;
+        rect.nearest(direction, rects.iter().filter(|(k, _)| *k != from).copied())
+    }
+
+    #[test]
+    fn test_nearest_tiled() {
+        let rects = tiled();
+
+        assert_eq!(nearest(1, Direction::Left, &rects), Some(0));
+        assert_eq!(nearest(2, Direction::Left, &rects), Some(0));
+        assert_eq!(nearest(1, Direction::Down, &rects), Some(2));
+        assert_eq!(nearest(2, Direction::Up, &rects), Some(1));
+        assert_eq!(nearest(0, Direction::Left, &rects), None);
+        assert_eq!(nearest(1, Direction::Right, &rects), None);
+
+        /* both overlap vertically and are equally far. lowest key wins */
+        assert_eq!(nearest(0, Direction::Right, &rects), Some(1));
+    }
+
+    #[test]
+    fn test_nearest_overlap_preferred() {
+        let from = Rect::new(0, 0, 10, 10);
+        let candidates = vec![
+            /* closer, but diagonal */
+            (0, Rect::new(12, 12, 10, 10)),
+            /* further, but in the same row */
+            (1, Rect::new(30, 5, 10, 10)),
+        ];
+
+        assert_eq!(from.nearest(Direction::Right, candidates), Some(1));
+    }
+
+    #[test]
+    fn test_nearest_floating() {
+        /* identical, stacked floating windows resolve to the lowest key no
+         * matter the order they are offered in */
+        let from = Rect::new(0, 0, 100, 100);
+        let float = Rect::new(60, 20, 30, 30);
+
+        assert_eq!(from.nearest(Direction::Right, vec![(7, float), (3, float), (5, float)]), Some(3));
+        assert_eq!(from.nearest(Direction::Right, vec![(5, float), (7, float), (3, float)]), Some(3));
+
+        /* a window centered on another is in no direction from it */
+        let centered = Rect::new(25, 25, 50, 50);
+        for direction in [Direction::Left, Direction::Right, Direction::Up, Direction::Down] {
+            assert_eq!(from.nearest(direction, vec![(0, centered)]), None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod centered_tests {
+    use super::*;
+
+    #[test]
+    fn test_centered() {
+        let screen = Rect::new(100, 50, 1000, 800);
+
+        assert_eq!(Rect::new(0, 0, 200, 100).centered(&screen), Rect::new(500, 400, 200, 100));
+        assert_eq!(Rect::new(0, 0, 201, 101).centered(&screen), Rect::new(499, 399, 201, 101));
+    }
+
+    #[test]
+    fn test_centered_overflow() {
+        let screen = Rect::new(100, 50, 1000, 800);
+
+        assert_eq!(Rect::new(0, 0, 2000, 100).centered(&screen), Rect::new(100, 400, 2000, 100));
+        assert_eq!(Rect::new(0, 0, 200, 900).centered(&screen), Rect::new(500, 50, 200, 900));
+    }
+}
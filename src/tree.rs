@@ -1,4 +1,6 @@
-use slab;
+use std::collections::VecDeque;
+
+use crate::arena::{Arena, Key};
 
 pub struct TreeNode<T> {
     pub value: T,
@@ -26,8 +28,8 @@ impl<T> std::fmt::Debug for TreeNode<T> {
 impl<T> TreeNode<T> {
     fn new(index: usize, value: T) -> Self {
         TreeNode {
-            value: value,
-            index: index,
+            value,
+            index,
             parent: None,
             left: None,
             right: None,
@@ -66,21 +68,18 @@ impl<T> TreeNode<T> {
 
 pub struct Tree<T> {
     root: usize,
-    slab: slab::Slab<TreeNode<T>>,
+    slab: Arena<TreeNode<T>>,
 }
 
 impl<T> Tree<T> {
     pub fn new(root: T) -> Self {
-        let mut slab = slab::Slab::new();
-
-        let index = slab.vacant_key();
-        let node = TreeNode::new(index, root);
-        slab.insert(node);
+        let mut tree = Tree {
+            root: 0,
+            slab: Arena::new(),
+        };
 
-        Tree {
-            root: index,
-            slab: slab,
-        }
+        tree.root = tree.orphan(root);
+        tree
     }
 
     pub fn root(&self) -> usize {
@@ -89,9 +88,35 @@ impl<T> Tree<T> {
 
     /// Create and insert an unattached tree node
     fn orphan(&mut self, value: T) -> usize {
-        let index = self.slab.vacant_key();
-        let node = TreeNode::new(index, value);
-        self.slab.insert(node)
+        let index = self.slab.vacant_key().index();
+        self.slab.insert(TreeNode::new(index, value)).index()
+    }
+
+    /// Remove a node from the slab, invalidating its key
+    fn free(&mut self, index: usize) -> TreeNode<T> {
+        let key = self.slab.key(index);
+        self.slab.remove(key).unwrap()
+    }
+
+    /// Get a node, if index is occupied
+    pub fn get(&self, index: usize) -> Option<&TreeNode<T>> {
+        self.slab.at(index)
+    }
+
+    /// Get a mutable node, if index is occupied
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut TreeNode<T>> {
+        self.slab.at_mut(index)
+    }
+
+    /// Current key of the node at index. Keys of removed nodes never match
+    /// whatever reuses their index
+    pub fn key(&self, index: usize) -> Key {
+        self.slab.key(index)
+    }
+
+    /// Check that key still refers to a node in this tree
+    pub fn contains(&self, key: Key) -> bool {
+        self.slab.contains(key)
     }
 
     /// Attach an orphan() to the tree at index
@@ -112,12 +137,85 @@ impl<T> Tree<T> {
             }
         }
 
-        let mut node = &mut self[orphan];
+        let node = &mut self[orphan];
         node.parent = Some(parent);
         node.left = sibling;
 
     }
 
+    /// Attach an orphan() to parent, just before its child right. Attaches
+    /// as the last child when right is None
+    fn adopt_before(&mut self, parent: usize, orphan: usize, right: Option<usize>) {
+        let right = match right {
+            Some(right) => right,
+            None => return self.adopt(parent, orphan),
+        };
+
+        let left = self[right].left.replace(orphan);
+
+        match left {
+            Some(i) => {
+                self[i].right = Some(orphan);
+            }
+            None => {
+                /* right was the first child */
+                self[parent].first = Some(orphan);
+            }
+        }
+
+        let node = &mut self[orphan];
+        node.parent = Some(parent);
+        node.left = left;
+        node.right = Some(right);
+    }
+
+    /// Check whether ancestor is index or one of its ancestors
+    pub fn is_ancestor(&self, ancestor: usize, mut index: usize) -> bool {
+        loop {
+            if index == ancestor {
+                return true;
+            }
+
+            match self[index].parent {
+                Some(parent) => index = parent,
+                None => return false,
+            }
+        }
+    }
+
+    /// Swap the positions of two nodes, along with their sub-trees. Nodes
+    /// keep their indices. Panics if one node contains the other
+    pub fn swap(&mut self, a: usize, b: usize) {
+        if a == b {
+            return;
+        }
+
+        if self.is_ancestor(a, b) || self.is_ancestor(b, a) {
+            panic!("Tree::swap: {} and {} contain each other", a, b);
+        }
+
+        if self[a].right == Some(b) {
+            /* adjacent: a, b -> b, a */
+            let parent = self[a].parent.unwrap();
+            self.unlink(b);
+            self.adopt_before(parent, b, Some(a));
+        } else if self[b].right == Some(a) {
+            let parent = self[b].parent.unwrap();
+            self.unlink(a);
+            self.adopt_before(parent, a, Some(b));
+        } else {
+            /* the right siblings are neither a nor b, so they stay put */
+            let (pa, ra) = (self[a].parent.unwrap(), self[a].right);
+            let (pb, rb) = (self[b].parent.unwrap(), self[b].right);
+
+            self.unlink(a);
+            self.unlink(b);
+
+            self.adopt_before(pb, a, rb);
+            self.adopt_before(pa, b, ra);
+        }
+    }
+
     /// Insert value into tree with given parent
     pub fn insert(&mut self, parent: usize, value: T) -> usize {
         let orphan = self.orphan(value);
@@ -126,16 +224,91 @@ impl<T> Tree<T> {
         orphan
     }
 
-    /// Remove a sub-tree from one tree and graft it into another
-    pub fn graft(&mut self, other: &mut Tree<T>, from: usize, to: usize) {
+    /// Child of parent at position, if there are that many children
+    pub fn nth_child(&self, parent: usize, position: usize) -> Option<usize> {
+        self.children(parent).nth(position)
+    }
+
+    /// Insert value as the child at position under parent. Positions past
+    /// the last child append
+    pub fn insert_at(&mut self, parent: usize, position: usize, value: T) -> usize {
+        let right = self.nth_child(parent, position);
+        let orphan = self.orphan(value);
+        self.adopt_before(parent, orphan, right);
+
+        orphan
+    }
+
+    /// Insert value just before sibling. Panics if sibling is the root
+    pub fn insert_before(&mut self, sibling: usize, value: T) -> usize {
+        let parent = self[sibling].parent.expect("Tree::insert_before: root has no siblings");
+        let orphan = self.orphan(value);
+        self.adopt_before(parent, orphan, Some(sibling));
+
+        orphan
+    }
+
+    /// Insert value just after sibling. Panics if sibling is the root
+    pub fn insert_after(&mut self, sibling: usize, value: T) -> usize {
+        let parent = self[sibling].parent.expect("Tree::insert_after: root has no siblings");
+        let right = self[sibling].right;
+        let orphan = self.orphan(value);
+        self.adopt_before(parent, orphan, right);
+
+        orphan
+    }
+
+    /// Move a node and its sub-tree to position under parent, keeping every
+    /// index. Positions past the last child append. Panics if parent is
+    /// inside the node being moved
+    pub fn move_to(&mut self, index: usize, parent: usize, position: usize) {
+        if self.is_ancestor(index, parent) {
+            panic!("Tree::move_to: {} is inside {}", parent, index);
+        }
+
+        self.unlink(index);
+
+        let right = self.nth_child(parent, position);
+        self.adopt_before(parent, index, right);
+    }
+
+    /// Rotate the children of parent n places towards the end, wrapping the
+    /// last children around to the front. Negative n rotates the other way
+    pub fn rotate_children(&mut self, parent: usize, n: isize) {
+        let mut children: Vec<_> = self.children(parent).collect();
+
+        if children.len() < 2 {
+            return;
+        }
+
+        let k = n.rem_euclid(children.len() as isize) as usize;
+        children.rotate_right(k);
+
+        /* relink the siblings in their new order */
+        for (i, child) in children.iter().enumerate() {
+            let node = &mut self[*child];
+            node.left = if i > 0 { Some(children[i - 1]) } else { None };
+            node.right = children.get(i + 1).copied();
+        }
+
+        let node = &mut self[parent];
+        node.first = children.first().copied();
+        node.last = children.last().copied();
+    }
+
+    /// Remove a sub-tree from one tree and graft it into another, returning
+    /// the new index of its root
+    pub fn graft(&mut self, other: &mut Tree<T>, from: usize, to: usize) -> usize {
         /* not the fastest way to do this, but the easiest to read */
         let children: Vec<_> = other.children(from).collect();
-        let node = other.slab.remove(from);
+        let node = other.free(from);
         let index = self.insert(to, node.value);
 
-        for child in children.into_iter().rev() {
+        for child in children.into_iter() {
             self.graft(other, child, index);
         }
+
+        index
     }
 
     /// Discard a node and its children from a tree with no further processing
@@ -143,7 +316,7 @@ impl<T> Tree<T> {
     fn discard(&mut self, index: usize) -> TreeNode<T> {
         let children: Vec<_> = self.children(index).collect();
 
-        let root = self.slab.remove(index);
+        let root = self.free(index);
 
         for child in children.into_iter() {
             self.discard(child);
@@ -152,51 +325,50 @@ impl<T> Tree<T> {
         root
     }
 
-    /// Extract a node from the tree and return its value, non-recursively
-    pub fn extract(&mut self, index: usize) -> T {
-        let root = self.slab.remove(index);
+    /// Detach a node from its parent and siblings, leaving it as an orphan
+    fn unlink(&mut self, index: usize) {
+        let root = &mut self[index];
+        let parent = root.parent.take();
+        let left = root.left.take();
+        let right = root.right.take();
 
         /* check the left sibling. if there isn't one, we are the first child in
          * parent. otherwise, update the right sibling. */
-        match root.left {
+        match left {
             Some(i) => {
                 /* we have a sibling to the left. give it our right value */
-                self[i].right = root.right;
+                self[i].right = right;
             },
             None => {
                 /* we are the first child for our parent.
                  * update parent.first to have our right value */
-                match root.parent {
-                    Some(j) => {
-                        self[j].first = root.right;
-                    },
-                    None => {
-                    }
+                if let Some(j) = parent {
+                    self[j].first = right;
                 }
             },
         }
 
         /* check the right sibling. if there isn't one, we are the last child in
          * parent. otherwise, update the left sibling. */
-        match root.right {
+        match right {
             Some(i) => {
                 /* we have a sibling to the right. give it our left value */
-                self[i].left = root.left;
+                self[i].left = left;
             },
             None => {
                 /* we are the last child for our parent.
                  * update parent.last to have our left value */
-                match root.parent {
-                    Some(j) => {
-                        self[j].last = root.left;
-                    },
-                    None => {
-                    }
+                if let Some(j) = parent {
+                    self[j].last = left;
                 }
             },
         }
+    }
 
-        root.value
+    /// Extract a node from the tree and return its value, non-recursively
+    pub fn extract(&mut self, index: usize) -> T {
+        self.unlink(index);
+        self.free(index).value
     }
 
     /// Extract a node from the tree and return its value, recursively discarding children
@@ -214,10 +386,11 @@ impl<T> Tree<T> {
     pub fn remove(&mut self, index: usize) -> Tree<T> {
         let children: Vec<_> = self.children(index).collect();
 
-        let root = self.slab.remove(index);
+        self.unlink(index);
+        let root = self.free(index);
         let mut tree = Tree::new(root.value);
 
-        for child in children.into_iter().rev() {
+        for child in children.into_iter() {
             tree.graft(self, child, tree.root);
         }
 
@@ -226,15 +399,18 @@ impl<T> Tree<T> {
 }
 
 impl<T> Tree<T> {
+    /// Children of index, left to right
     pub fn children<'a>(&'a self, index: usize) -> Children<'a, T> {
-        let child = self[index].first;
+        let node = &self[index];
 
         Children {
             tree: self,
-            index: child,
+            front: node.first,
+            back: node.last,
         }
     }
 
+    /// Sub-tree at index in pre-order, starting with index
     pub fn iter_at<'a>(&'a self, index: usize) -> IterAt<'a, T> {
         IterAt {
             tree: self,
@@ -242,12 +418,122 @@ impl<T> Tree<T> {
         }
     }
 
-    pub fn iter(&self) -> slab::Iter<'_, TreeNode<T>> {
-        self.slab.iter()
+    /// Parent of index, then its parent, up to the root
+    pub fn ancestors<'a>(&'a self, index: usize) -> Ancestors<'a, T> {
+        Ancestors {
+            tree: self,
+            index: self[index].parent,
+        }
+    }
+
+    /// Sub-tree at index in post-order, ending with index
+    pub fn post_order<'a>(&'a self, index: usize) -> PostOrder<'a, T> {
+        PostOrder {
+            tree: self,
+            stack: vec![(index, false)],
+        }
+    }
+
+    /// Sub-tree at index level by level, starting with index
+    pub fn breadth_first<'a>(&'a self, index: usize) -> BreadthFirst<'a, T> {
+        BreadthFirst {
+            tree: self,
+            queue: VecDeque::from([index]),
+        }
+    }
+
+    /// Nodes without children in the sub-tree at index, left to right
+    pub fn leaves<'a>(&'a self, index: usize) -> Leaves<'a, T> {
+        Leaves {
+            inner: self.iter_at(index),
+        }
+    }
+
+    /// Number of ancestors of index. The root has depth 0
+    pub fn depth(&self, index: usize) -> usize {
+        self.ancestors(index).count()
+    }
+
+    /// Position of index among its siblings. The root is at position 0
+    pub fn sibling_position(&self, index: usize) -> usize {
+        let mut position = 0;
+        let mut node = &self[index];
+
+        while let Some(left) = node.left {
+            position += 1;
+            node = &self[left];
+        }
+
+        position
+    }
+
+    /// Deepest node that is an ancestor of both a and b, or either of them
+    /// if it contains the other
+    pub fn common_ancestor(&self, a: usize, b: usize) -> usize {
+        let (mut a, mut b) = (a, b);
+        let (mut da, mut db) = (self.depth(a), self.depth(b));
+
+        /* climb to the same depth, then climb together */
+        while da > db {
+            a = self[a].parent.unwrap();
+            da -= 1;
+        }
+
+        while db > da {
+            b = self[b].parent.unwrap();
+            db -= 1;
+        }
+
+        while a != b {
+            a = self[a].parent.unwrap();
+            b = self[b].parent.unwrap();
+        }
+
+        a
     }
 
-    pub fn iter_mut<'a>(&'a mut self) -> slab::IterMut<'a, TreeNode<T>> {
-        self.slab.iter_mut()
+    /// Visit the ancestors of index, nearest first
+    pub fn ancestors_mut<F: FnMut(usize, &mut T)>(&mut self, index: usize, f: F) {
+        let indices: Vec<_> = self.ancestors(index).collect();
+        self.visit_mut(indices, f);
+    }
+
+    /// Visit the sub-tree at index in pre-order
+    pub fn iter_at_mut<F: FnMut(usize, &mut T)>(&mut self, index: usize, f: F) {
+        let indices: Vec<_> = self.iter_at(index).collect();
+        self.visit_mut(indices, f);
+    }
+
+    /// Visit the sub-tree at index in post-order
+    pub fn post_order_mut<F: FnMut(usize, &mut T)>(&mut self, index: usize, f: F) {
+        let indices: Vec<_> = self.post_order(index).collect();
+        self.visit_mut(indices, f);
+    }
+
+    /// Visit the sub-tree at index level by level
+    pub fn breadth_first_mut<F: FnMut(usize, &mut T)>(&mut self, index: usize, f: F) {
+        let indices: Vec<_> = self.breadth_first(index).collect();
+        self.visit_mut(indices, f);
+    }
+
+    /// Visit the leaves of the sub-tree at index, left to right
+    pub fn leaves_mut<F: FnMut(usize, &mut T)>(&mut self, index: usize, f: F) {
+        let indices: Vec<_> = self.leaves(index).collect();
+        self.visit_mut(indices, f);
+    }
+
+    fn visit_mut<F: FnMut(usize, &mut T)>(&mut self, indices: Vec<usize>, mut f: F) {
+        for i in indices {
+            f(i, &mut self[i].value);
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &TreeNode<T>)> {
+        self.slab.iter().map(|(key, node)| (key.index(), node))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (usize, &mut TreeNode<T>)> {
+        self.slab.iter_mut().map(|(key, node)| (key.index(), node))
     }
 }
 
@@ -267,16 +553,38 @@ impl<T> std::ops::IndexMut<usize> for Tree<T> {
 
 pub struct Children<'a, T> {
     tree: &'a Tree<T>,
-    index: Option<usize>,
+    front: Option<usize>,
+    back: Option<usize>,
 }
 
 impl<'a, T> Iterator for Children<'a, T> {
     type Item = usize;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let index = self.index?;
-        let node = &self.tree[index];
-        self.index = node.next_sibling();
+        let index = self.front?;
+
+        /* the ends met, the iterator is exhausted */
+        if self.front == self.back {
+            self.front = None;
+            self.back = None;
+        } else {
+            self.front = self.tree[index].next_sibling();
+        }
+
+        Some(index)
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for Children<'a, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let index = self.back?;
+
+        if self.front == self.back {
+            self.front = None;
+            self.back = None;
+        } else {
+            self.back = self.tree[index].previous_sibling();
+        }
 
         Some(index)
     }
@@ -293,47 +601,427 @@ impl<'a, T> Iterator for IterAt<'a, T> {
     fn next(&mut self) -> Option<Self::Item> {
         let index = self.stack.pop()?;
 
-        self.stack.extend(self.tree.children(index));
+        /* push in reverse, so the first child is visited first */
+        self.stack.extend(self.tree.children(index).rev());
+
+        Some(index)
+    }
+}
+
+pub struct Ancestors<'a, T> {
+    tree: &'a Tree<T>,
+    index: Option<usize>,
+}
+
+impl<'a, T> Iterator for Ancestors<'a, T> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let index = self.index?;
+        self.index = self.tree[index].parent();
+
+        Some(index)
+    }
+}
+
+pub struct PostOrder<'a, T> {
+    tree: &'a Tree<T>,
+    /* nodes, and whether their children were already pushed */
+    stack: Vec<(usize, bool)>,
+}
+
+impl<'a, T> Iterator for PostOrder<'a, T> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (index, expanded) = self.stack.pop()?;
+
+            if expanded {
+                return Some(index);
+            }
+
+            self.stack.push((index, true));
+            self.stack.extend(self.tree.children(index).rev().map(|i| (i, false)));
+        }
+    }
+}
+
+pub struct BreadthFirst<'a, T> {
+    tree: &'a Tree<T>,
+    queue: VecDeque<usize>,
+}
+
+impl<'a, T> Iterator for BreadthFirst<'a, T> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let index = self.queue.pop_front()?;
+
+        self.queue.extend(self.tree.children(index));
 
         Some(index)
     }
 }
 
+pub struct Leaves<'a, T> {
+    inner: IterAt<'a, T>,
+}
+
+impl<'a, T> Iterator for Leaves<'a, T> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let tree = self.inner.tree;
+        self.inner.find(|i| tree[*i].child().is_none())
+    }
+}
+
+#[cfg(test)]
+mod reorder_tests {
+    use super::*;
+
+    fn values<T: Copy>(tree: &Tree<T>, index: usize) -> Vec<T> {
+        tree.children(index).map(|i| tree[i].value).collect()
+    }
+
+    /* check every sibling and parent link under index */
+    fn check<T>(tree: &Tree<T>, index: usize) {
+        let children: Vec<_> = tree.children(index).collect();
+
+        assert_eq!(tree[index].first, children.first().copied());
+        assert_eq!(tree[index].last, children.last().copied());
+
+        for (i, child) in children.iter().enumerate() {
+            assert_eq!(tree[*child].parent, Some(index));
+            assert_eq!(tree[*child].left, if i > 0 { Some(children[i - 1]) } else { None });
+            check(tree, *child);
+        }
+    }
+
+    fn tree() -> (Tree<u32>, Vec<usize>) {
+        let mut tree = Tree::new(0);
+        let root = tree.root();
+
+        let a = tree.insert(root, 1);
+        let b = tree.insert(root, 2);
+        let c = tree.insert(root, 3);
+        let d = tree.insert(b, 4);
+        let e = tree.insert(b, 5);
+
+        (tree, vec![root, a, b, c, d, e])
+    }
+
+    #[test]
+    fn test_swap_adjacent() {
+        let (mut tree, n) = tree();
+
+        tree.swap(n[1], n[2]);
+        assert_eq!(values(&tree, n[0]), vec![2, 1, 3]);
+        check(&tree, n[0]);
+
+        tree.swap(n[1], n[2]);
+        assert_eq!(values(&tree, n[0]), vec![1, 2, 3]);
+        check(&tree, n[0]);
+    }
+
+    #[test]
+    fn test_swap_apart() {
+        let (mut tree, n) = tree();
+
+        tree.swap(n[1], n[3]);
+        assert_eq!(values(&tree, n[0]), vec![3, 2, 1]);
+        check(&tree, n[0]);
+
+        /* across parents, keeping indices and sub-trees */
+        tree.swap(n[3], n[5]);
+        assert_eq!(values(&tree, n[0]), vec![5, 2, 1]);
+        assert_eq!(values(&tree, n[2]), vec![4, 3]);
+        assert_eq!(tree[n[3]].parent(), Some(n[2]));
+        check(&tree, n[0]);
+
+        tree.swap(n[2], n[5]);
+        assert_eq!(values(&tree, n[0]), vec![2, 5, 1]);
+        assert_eq!(values(&tree, n[2]), vec![4, 3]);
+        check(&tree, n[0]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_swap_ancestor() {
+        let (mut tree, n) = tree();
+        tree.swap(n[2], n[4]);
+    }
+
+    #[test]
+    fn test_insert_at() {
+        let (mut tree, n) = tree();
+
+        tree.insert_at(n[0], 0, 10);
+        tree.insert_at(n[0], 2, 11);
+        tree.insert_at(n[0], 100, 12);
+        assert_eq!(values(&tree, n[0]), vec![10, 1, 11, 2, 3, 12]);
+        check(&tree, n[0]);
+
+        let leaf = tree.insert_at(n[4], 0, 13);
+        assert_eq!(values(&tree, n[4]), vec![13]);
+        assert_eq!(tree.nth_child(n[4], 0), Some(leaf));
+        assert_eq!(tree.nth_child(n[4], 1), None);
+        check(&tree, n[0]);
+    }
+
+    #[test]
+    fn test_insert_before_after() {
+        let (mut tree, n) = tree();
+
+        tree.insert_before(n[1], 10);
+        tree.insert_after(n[1], 11);
+        tree.insert_after(n[3], 12);
+        tree.insert_before(n[5], 13);
+        assert_eq!(values(&tree, n[0]), vec![10, 1, 11, 2, 3, 12]);
+        assert_eq!(values(&tree, n[2]), vec![4, 13, 5]);
+        check(&tree, n[0]);
+    }
+
+    #[test]
+    fn test_move_to() {
+        let (mut tree, n) = tree();
+
+        /* within the same parent */
+        tree.move_to(n[1], n[0], 100);
+        assert_eq!(values(&tree, n[0]), vec![2, 3, 1]);
+        tree.move_to(n[1], n[0], 0);
+        assert_eq!(values(&tree, n[0]), vec![1, 2, 3]);
+        check(&tree, n[0]);
+
+        /* to another parent, keeping the index */
+        tree.move_to(n[3], n[2], 1);
+        assert_eq!(values(&tree, n[0]), vec![1, 2]);
+        assert_eq!(values(&tree, n[2]), vec![4, 3, 5]);
+        assert_eq!(tree[n[3]].value, 3);
+        check(&tree, n[0]);
+
+        /* with a sub-tree */
+        tree.move_to(n[2], n[1], 0);
+        assert_eq!(values(&tree, n[0]), vec![1]);
+        assert_eq!(values(&tree, n[1]), vec![2]);
+        assert_eq!(values(&tree, n[2]), vec![4, 3, 5]);
+        check(&tree, n[0]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_move_into_self() {
+        let (mut tree, n) = tree();
+        tree.move_to(n[2], n[4], 0);
+    }
+
+    #[test]
+    fn test_rotate_children() {
+        let (mut tree, n) = tree();
+
+        tree.rotate_children(n[0], 1);
+        assert_eq!(values(&tree, n[0]), vec![3, 1, 2]);
+        check(&tree, n[0]);
+
+        tree.rotate_children(n[0], -2);
+        assert_eq!(values(&tree, n[0]), vec![2, 3, 1]);
+        check(&tree, n[0]);
+
+        tree.rotate_children(n[0], 7);
+        assert_eq!(values(&tree, n[0]), vec![1, 2, 3]);
+        check(&tree, n[0]);
+
+        /* nothing to rotate */
+        tree.rotate_children(n[4], 3);
+        assert_eq!(values(&tree, n[4]), Vec::<u32>::new());
+        check(&tree, n[0]);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    fn children<T: Copy>(tree: &Tree<T>, index: usize) -> Vec<T> {
-        let i: Vec<_> = tree.children(index).collect();
-        i.into_iter().map(|i| tree.get(&i).unwrap().value).collect()
+    fn values<T: Copy, I: Iterator<Item = usize>>(tree: &Tree<T>, indices: I) -> Vec<T> {
+        indices.map(|i| tree[i].value).collect()
     }
 
-    fn iter<T: Copy>(tree: &Tree<T>, index: usize) -> Vec<T> {
-        let i: Vec<_> = tree.iter(index).collect();
-        i.into_iter().map(|i| tree.get(&i).unwrap().value).collect()
+    /* 1
+     * ├── 2
+     * │   ├── 3
+     * │   └── 4
+     * │       ├── 5
+     * │       ├── 6
+     * │       └── 7
+     * └── 8 */
+    fn tree() -> (Tree<u32>, [usize; 8]) {
+        let mut tree = Tree::new(1);
+        let one = tree.root();
+
+        let two = tree.insert(one, 2);
+        let three = tree.insert(two, 3);
+        let four = tree.insert(two, 4);
+
+        let five = tree.insert(four, 5);
+        let six = tree.insert(four, 6);
+        let seven = tree.insert(four, 7);
+
+        let eight = tree.insert(one, 8);
+
+        (tree, [one, two, three, four, five, six, seven, eight])
     }
 
     #[test]
     fn test_tree() {
-        let mut tree = Tree::new(1);
+        let (tree, [one, two, _, four, ..]) = tree();
+
+        assert_eq!(values(&tree, tree.children(one)), vec![2, 8]);
+        assert_eq!(values(&tree, tree.children(two)), vec![3, 4]);
+        assert_eq!(values(&tree, tree.children(four)), vec![5, 6, 7]);
+        assert_eq!(values(&tree, tree.children(four).rev()), vec![7, 6, 5]);
+        assert_eq!(values(&tree, tree.iter_at(one)), vec![1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(values(&tree, tree.iter_at(four)), vec![4, 5, 6, 7]);
+    }
 
-        let two = tree.insert(&tree.root(), 2).unwrap();
-        tree.insert(&two, 3).unwrap();
-        let four = tree.insert(&two, 4).unwrap();
+    #[test]
+    fn test_children_both_ends() {
+        let (tree, [_, _, _, four, ..]) = tree();
+
+        let mut children = tree.children(four);
+        assert_eq!(children.next().map(|i| tree[i].value), Some(5));
+        assert_eq!(children.next_back().map(|i| tree[i].value), Some(7));
+        assert_eq!(children.next().map(|i| tree[i].value), Some(6));
+        assert_eq!(children.next(), None);
+        assert_eq!(children.next_back(), None);
+    }
+
+    #[test]
+    fn test_remove() {
+        let (mut tree, [one, two, ..]) = tree();
 
-        tree.insert(&four, 5).unwrap();
-        tree.insert(&four, 6).unwrap();
-        tree.insert(&four, 7).unwrap();
+        let new = tree.remove(two);
+        assert_eq!(values(&tree, tree.iter_at(one)), vec![1, 8]);
+        assert_eq!(values(&new, new.iter_at(new.root())), vec![2, 3, 4, 5, 6, 7]);
+
+        /* grafting back keeps the order of every level */
+        let mut new = new;
+        let root = new.root();
+        let two = tree.graft(&mut new, root, one);
+        assert_eq!(values(&tree, tree.iter_at(one)), vec![1, 8, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(tree.depth(tree.leaves(two).last().unwrap()), 3);
+    }
+
+    #[test]
+    fn test_extract_prune() {
+        let (mut tree, [one, two, three, four, ..]) = tree();
+
+        assert_eq!(tree.extract(three), 3);
+        assert_eq!(values(&tree, tree.children(two)), vec![4]);
+
+        assert_eq!(tree.prune(four), 4);
+        assert_eq!(values(&tree, tree.iter_at(one)), vec![1, 2, 8]);
+        assert_eq!(tree.iter().count(), 3);
+    }
+
+    #[test]
+    fn test_generation() {
+        let (mut tree, [one, _, three, ..]) = tree();
+
+        let key = tree.key(three);
+        tree.prune(three);
+        assert!(tree.get(three).is_none());
+        assert!(!tree.contains(key));
+
+        /* the slot is reused with a new generation */
+        let nine = tree.insert(one, 9);
+        assert_eq!(nine, three);
+        assert_ne!(tree.key(nine), key);
+        assert!(!tree.contains(key));
+    }
+
+    #[test]
+    fn test_ancestors() {
+        let (tree, [one, two, three, four, five, ..]) = tree();
+
+        assert_eq!(tree.ancestors(five).collect::<Vec<_>>(), vec![four, two, one]);
+        assert_eq!(tree.ancestors(three).collect::<Vec<_>>(), vec![two, one]);
+        assert_eq!(tree.ancestors(one).count(), 0);
+    }
+
+    #[test]
+    fn test_post_order() {
+        let (tree, [one, _, _, four, ..]) = tree();
+
+        assert_eq!(values(&tree, tree.post_order(one)), vec![3, 5, 6, 7, 4, 2, 8, 1]);
+        assert_eq!(values(&tree, tree.post_order(four)), vec![5, 6, 7, 4]);
+    }
+
+    #[test]
+    fn test_breadth_first() {
+        let (tree, [one, two, ..]) = tree();
+
+        assert_eq!(values(&tree, tree.breadth_first(one)), vec![1, 2, 8, 3, 4, 5, 6, 7]);
+        assert_eq!(values(&tree, tree.breadth_first(two)), vec![2, 3, 4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn test_leaves() {
+        let (tree, [one, _, three, four, ..]) = tree();
+
+        assert_eq!(values(&tree, tree.leaves(one)), vec![3, 5, 6, 7, 8]);
+        assert_eq!(values(&tree, tree.leaves(four)), vec![5, 6, 7]);
+        assert_eq!(values(&tree, tree.leaves(three)), vec![3]);
+    }
+
+    #[test]
+    fn test_depth_position() {
+        let (tree, [one, two, three, four, five, six, seven, eight]) = tree();
+
+        assert_eq!(tree.depth(one), 0);
+        assert_eq!(tree.depth(two), 1);
+        assert_eq!(tree.depth(seven), 3);
+
+        assert_eq!(tree.sibling_position(one), 0);
+        assert_eq!(tree.sibling_position(eight), 1);
+        assert_eq!(tree.sibling_position(three), 0);
+        assert_eq!(tree.sibling_position(four), 1);
+        assert_eq!(tree.sibling_position(five), 0);
+        assert_eq!(tree.sibling_position(six), 1);
+        assert_eq!(tree.sibling_position(seven), 2);
+    }
+
+    #[test]
+    fn test_common_ancestor() {
+        let (tree, [one, two, three, four, five, _, seven, eight]) = tree();
+
+        assert_eq!(tree.common_ancestor(five, seven), four);
+        assert_eq!(tree.common_ancestor(three, seven), two);
+        assert_eq!(tree.common_ancestor(five, eight), one);
+        assert_eq!(tree.common_ancestor(four, five), four);
+        assert_eq!(tree.common_ancestor(five, four), four);
+        assert_eq!(tree.common_ancestor(three, three), three);
+        assert_eq!(tree.common_ancestor(one, seven), one);
+    }
+
+    #[test]
+    fn test_visit_mut() {
+        let (mut tree, [one, two, _, four, five, ..]) = tree();
 
-        tree.insert(&tree.root(), 8).unwrap();
+        let mut order = vec![];
+        tree.post_order_mut(two, |i, value| {
+            order.push(i);
+            *value *= 10;
+        });
+        assert_eq!(order, tree.post_order(two).collect::<Vec<_>>());
+        assert_eq!(values(&tree, tree.iter_at(one)), vec![1, 20, 30, 40, 50, 60, 70, 8]);
 
-        assert_eq!(children(&tree, &tree.root()), vec![8, 2]);
-        assert_eq!(children(&tree, &two), vec![4, 3]);
-        assert_eq!(children(&tree, &four), vec![7, 6, 5]);
-        assert_eq!(iter(&tree, &tree.root), vec![1, 2, 3, 4, 5, 6, 7, 8]);
+        tree.ancestors_mut(five, |_, value| *value += 1);
+        assert_eq!(values(&tree, tree.iter_at(one)), vec![2, 21, 30, 41, 50, 60, 70, 8]);
 
-        let new = tree.remove(&two).unwrap();
-        assert_eq!(iter(&tree, &tree.root), vec![1, 8]);
-        assert_eq!(iter(&new, &new.root), vec![2, 3, 4, 5, 6, 7]);
+        tree.leaves_mut(four, |_, value| *value = 0);
+        tree.breadth_first_mut(one, |_, value| *value += 1);
+        tree.iter_at_mut(four, |_, value| *value *= 2);
+        assert_eq!(values(&tree, tree.iter_at(one)), vec![3, 22, 31, 84, 2, 2, 2, 9]);
     }
 }
@@ -1,8 +1,60 @@
-use xcb::x;
+use xcb::{x, Xid};
 
-use crate::rect::Rect;
+use crate::rect::{Rect, Size};
 use crate::error::Error;
 use crate::manager::Connection;
+use crate::workspace::WorkspaceId;
+use crate::container::ContainerId;
+use crate::ewmh::Strut;
+use crate::hints::SizeHints;
+
+/// Events selected on every managed client window
+const CLIENT_EVENTS: x::EventMask = x::EventMask::FOCUS_CHANGE.union(x::EventMask::PROPERTY_CHANGE);
+
+/// Functional type of a window, from _NET_WM_WINDOW_TYPE
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum WindowType {
+    Normal,
+    Dialog,
+    Dock,
+    Splash,
+    Utility,
+    Toolbar,
+    Notification,
+    Desktop,
+}
+
+impl WindowType {
+    /// Whether windows of this type belong in a layout
+    pub fn tiled(&self) -> bool {
+        !matches!(self, WindowType::Dock | WindowType::Desktop | WindowType::Notification)
+    }
+}
+
+/// ICCCM WM_STATE of a managed window
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum WmState {
+    /// Not mapped, and not managed any more
+    Withdrawn = 0,
+    /// Mapped
+    Normal = 1,
+    /// Unmapped by the manager, on a hidden workspace or minimized
+    Iconic = 3,
+}
+
+/// How a client takes the input focus, ICCCM 4.1.7
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FocusModel {
+    /// Never takes the focus
+    NoInput,
+    /// Takes the focus when the manager sets it
+    Passive,
+    /// Takes the focus when the manager sets it, and is told through
+    /// WM_TAKE_FOCUS
+    LocallyActive,
+    /// Sets the focus itself when asked through WM_TAKE_FOCUS
+    GloballyActive,
+}
 
 pub struct Window {
     conn: Connection,
@@ -11,6 +63,38 @@ pub struct Window {
     visible: bool,
     managed: bool,
     selectable: bool,
+    floating: bool,
+    weight: f32,
+    kind: WindowType,
+    /* WM_PROTOCOLS the client takes part in */
+    protocols: Vec<x::Atom>,
+    /* the WM_HINTS input flag. clients that leave it out get the focus */
+    input: bool,
+    /* space reserved by a dock */
+    strut: Option<Strut>,
+    /* WM_NORMAL_HINTS */
+    hints: SizeHints,
+    /* whether layout cells are shrunk to fit the size hints */
+    constrained: bool,
+    fullscreen: bool,
+    /* iconified, either by the user or by the client */
+    minimized: bool,
+    /* the geometry to return to when leaving fullscreen */
+    tiled: Option<Rect>,
+    /* the workspace the window was last placed on */
+    workspace: Option<WorkspaceId>,
+    /* the window a dialog belongs to, from WM_TRANSIENT_FOR */
+    transient_for: Option<ContainerId>,
+    /* the two parts of WM_CLASS */
+    instance: Option<String>,
+    class: Option<String>,
+    /* _NET_WM_NAME, or WM_NAME for clients without it */
+    title: Option<String>,
+    role: Option<String>,
+    pid: Option<u32>,
+    /* number of UnmapNotify events caused by hide() that have not been
+     * received yet. these must not be mistaken for the client withdrawing */
+    unmaps: usize,
 }
 
 impl Window {
@@ -22,23 +106,175 @@ impl Window {
         self.window
     }
 
+    pub fn visible(&self) -> bool {
+        self.visible
+    }
+
     pub fn managed(&self) -> bool {
         self.managed
     }
+
+    pub fn selectable(&self) -> bool {
+        self.selectable
+    }
+
+    pub(crate) fn set_selectable(&mut self, selectable: bool) {
+        self.selectable = selectable;
+    }
+
+    /// Type the client gave the window. Normal when it gave none
+    pub fn window_type(&self) -> WindowType {
+        self.kind
+    }
+
+    pub fn floating(&self) -> bool {
+        self.floating
+    }
+
+    pub fn set_floating(&mut self, floating: bool) {
+        self.floating = floating;
+    }
+
+    /// Relative share of the parent layout's scope
+    pub fn weight(&self) -> f32 {
+        self.weight
+    }
+
+    pub fn set_weight(&mut self, weight: f32) {
+        self.weight = weight;
+    }
+
+    /// Whether the client listed protocol in WM_PROTOCOLS
+    pub fn supports(&self, protocol: x::Atom) -> bool {
+        self.protocols.contains(&protocol)
+    }
+
+    /// Focus model from the WM_HINTS input flag and WM_TAKE_FOCUS
+    pub fn focus_model(&self) -> FocusModel {
+        let take_focus = self.supports(self.conn.atoms().wm_take_focus);
+
+        match (self.input, take_focus) {
+            (false, false) => FocusModel::NoInput,
+            (true, false) => FocusModel::Passive,
+            (true, true) => FocusModel::LocallyActive,
+            (false, true) => FocusModel::GloballyActive,
+        }
+    }
+
+    /// Space the window reserves along the screen edges. Only docks have one
+    pub fn strut(&self) -> Option<Strut> {
+        self.strut
+    }
+
+    /// Size preferences the client set in WM_NORMAL_HINTS
+    pub fn size_hints(&self) -> &SizeHints {
+        &self.hints
+    }
+
+    /// Whether the window is sized to its hints inside a layout cell,
+    /// rather than stretched over the whole cell
+    pub fn constrained(&self) -> bool {
+        self.constrained
+    }
+
+    pub fn set_constrained(&mut self, constrained: bool) {
+        self.constrained = constrained;
+    }
+
+    /// Whether the window covers its whole monitor
+    pub fn fullscreen(&self) -> bool {
+        self.fullscreen
+    }
+
+    /// Whether the window is minimized. Minimized windows stay hidden and
+    /// are left out of arrangement until restored
+    pub fn minimized(&self) -> bool {
+        self.minimized
+    }
+
+    /// Workspace the window was last placed on. Kept while the window is
+    /// detached from the tree
+    pub fn workspace(&self) -> Option<WorkspaceId> {
+        self.workspace
+    }
+
+    pub(crate) fn set_workspace(&mut self, workspace: Option<WorkspaceId>) {
+        self.workspace = workspace;
+    }
+
+    /// Instance part of WM_CLASS, usually the name the client was run as
+    pub fn instance(&self) -> Option<&str> {
+        self.instance.as_deref()
+    }
+
+    /// Class part of WM_CLASS, naming the application
+    pub fn class(&self) -> Option<&str> {
+        self.class.as_deref()
+    }
+
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    /// WM_WINDOW_ROLE, telling apart windows of the same client
+    pub fn role(&self) -> Option<&str> {
+        self.role.as_deref()
+    }
+
+    /// Process id the client gave in _NET_WM_PID
+    pub fn pid(&self) -> Option<u32> {
+        self.pid
+    }
+
+    /// Container the window is a transient of. It may have been removed
+    /// since
+    pub fn transient_for(&self) -> Option<ContainerId> {
+        self.transient_for
+    }
+
+    pub(crate) fn set_transient_for(&mut self, parent: Option<ContainerId>) {
+        self.transient_for = parent;
+    }
 }
 
 impl Window {
     pub fn new(conn: Connection, window: x::Window, size: Rect, managed: bool, selectable: bool) -> Self {
         Self {
-            conn: conn,
-            window: window,
-            size: size,
-            managed: managed,
+            conn,
+            window,
+            size,
+            managed,
             visible: false,
-            selectable: selectable,
+            selectable,
+            floating: false,
+            weight: 1.0,
+            kind: WindowType::Normal,
+            protocols: vec![],
+            input: true,
+            strut: None,
+            hints: SizeHints::default(),
+            constrained: true,
+            fullscreen: false,
+            minimized: false,
+            tiled: None,
+            workspace: None,
+            transient_for: None,
+            instance: None,
+            class: None,
+            title: None,
+            role: None,
+            pid: None,
+            unmaps: 0,
         }
     }
 
+    /// Mark the window as already mapped, without sending a request. Used
+    /// for windows that were visible before the manager took over
+    pub(crate) fn mapped(&mut self) -> Result<(), Error> {
+        self.visible = true;
+        self.set_wm_state(WmState::Normal)
+    }
+
     pub fn show(&mut self) -> Result<(), Error> {
         if !self.visible {
             let cookie = self.conn.send_request_checked(&x::MapWindow {
@@ -46,6 +282,7 @@ impl Window {
             });
 
             self.conn.check_request(cookie)?;
+            self.set_wm_state(WmState::Normal)?;
         }
 
         self.visible = true;
@@ -54,12 +291,14 @@ impl Window {
     }
 
     pub fn hide(&mut self) -> Result<(), Error> {
-        if !self.visible {
+        if self.visible {
             let cookie = self.conn.send_request_checked(&x::UnmapWindow {
                 window: self.window,
             });
 
             self.conn.check_request(cookie)?;
+            self.set_wm_state(WmState::Iconic)?;
+            self.unmaps += 1;
         }
 
         self.visible = false;
@@ -67,6 +306,56 @@ impl Window {
         Ok(())
     }
 
+    /// Publish the ICCCM state of a managed window in WM_STATE
+    pub(crate) fn set_wm_state(&self, state: WmState) -> Result<(), Error> {
+        if !self.managed {
+            return Ok(());
+        }
+
+        let atom = self.conn.atoms().wm_state;
+
+        /* the state is followed by the icon window, which we never use */
+        let cookie = self.conn.send_request_checked(&x::ChangeProperty {
+            mode: x::PropMode::Replace,
+            window: self.window,
+            property: atom,
+            r#type: atom,
+            data: &[state as u32, 0],
+        });
+
+        self.conn.check_request(cookie)?;
+
+        Ok(())
+    }
+
+    /// Minimize or restore the window. Minimizing hides it, restoring
+    /// leaves showing it to the layout
+    pub(crate) fn set_minimized(&mut self, minimized: bool) -> Result<(), Error> {
+        if self.minimized == minimized {
+            return Ok(());
+        }
+
+        self.minimized = minimized;
+
+        if minimized {
+            self.hide()?;
+        }
+
+        self.update_state()
+    }
+
+    /// Consume one pending unmap caused by hide(). Returns false if the
+    /// unmap was not ours, meaning the client withdrew the window itself
+    pub fn expect_unmap(&mut self) -> bool {
+        if self.unmaps > 0 {
+            self.unmaps -= 1;
+            true
+        } else {
+            self.visible = false;
+            false
+        }
+    }
+
     pub fn resize(&mut self, size: Rect) -> Result<(), Error> {
         if self.size != size {
 
@@ -88,15 +377,477 @@ impl Window {
         Ok(())
     }
 
-    pub fn focus(&self) -> Result<(), Error> {
-        let cookie = self.conn.send_request_checked(&x::SetInputFocus {
-            revert_to: x::InputFocus::PointerRoot,
-            focus: self.window,
-            time: x::CURRENT_TIME,
+    /// Resize to the largest size the hints allow within cell, centered
+    /// in it
+    pub fn resize_constrained(&mut self, cell: Rect) -> Result<(), Error> {
+        let size = self.hints.constrain(Size { w: cell.w, h: cell.h });
+
+        self.resize(Rect::new(0, 0, size.w, size.h).centered(&cell))
+    }
+
+    /// Resize to the cell a layout allocated. A fullscreen window stays
+    /// on screen, and only remembers the cell for when it leaves fullscreen
+    pub(crate) fn place(&mut self, size: Rect, screen: Rect) -> Result<(), Error> {
+        if self.fullscreen {
+            self.tiled = Some(size);
+            self.resize(screen)
+        } else if self.constrained {
+            self.resize_constrained(size)
+        } else {
+            self.resize(size)
+        }
+    }
+
+    /// Enter or leave fullscreen. Entering covers screen, leaving restores
+    /// the geometry the window had before
+    pub(crate) fn set_fullscreen(&mut self, fullscreen: bool, screen: Rect) -> Result<(), Error> {
+        if self.fullscreen == fullscreen {
+            return Ok(());
+        }
+
+        self.fullscreen = fullscreen;
+
+        if fullscreen {
+            self.tiled = Some(self.size);
+            self.resize(screen)?;
+        } else if let Some(tiled) = self.tiled.take() {
+            self.resize(tiled)?;
+        }
+
+        self.update_state()
+    }
+
+    /// Stack the window above its siblings. Container::raise also keeps the
+    /// EWMH stacking list in step
+    pub(crate) fn raise(&self) -> Result<(), Error> {
+        let cookie = self.conn.send_request_checked(&x::ConfigureWindow {
+            window: self.window,
+            value_list: &[x::ConfigWindow::StackMode(x::StackMode::Above)],
+        });
+
+        self.conn.check_request(cookie)?;
+
+        Ok(())
+    }
+
+    /// Stack the window below its siblings. Container::lower also keeps the
+    /// EWMH stacking list in step
+    pub(crate) fn lower(&self) -> Result<(), Error> {
+        let cookie = self.conn.send_request_checked(&x::ConfigureWindow {
+            window: self.window,
+            value_list: &[x::ConfigWindow::StackMode(x::StackMode::Below)],
         });
 
         self.conn.check_request(cookie)?;
 
         Ok(())
     }
+
+    /// Request a property of the window, without waiting for the reply
+    fn request(&self, property: x::Atom, r#type: x::Atom, length: u32) -> x::GetPropertyCookie {
+        self.conn.send_request(&x::GetProperty {
+            delete: false,
+            window: self.window,
+            property,
+            r#type,
+            long_offset: 0,
+            long_length: length,
+        })
+    }
+
+    /// Follow the window's property changes and read every property the
+    /// manager uses, sending all requests before waiting for any reply.
+    /// Returns false if the window is already gone
+    pub(crate) fn load(&mut self) -> Result<bool, Error> {
+        let atoms = *self.conn.atoms();
+
+        /* changes made after the requests below are seen as events */
+        let select = self.conn.send_request_checked(&x::ChangeWindowAttributes {
+            window: self.window,
+            value_list: &[x::Cw::EventMask(CLIENT_EVENTS)],
+        });
+
+        let cookies = [
+            self.request(atoms.net_wm_window_type, x::ATOM_ATOM, 32),
+            self.request(atoms.wm_protocols, x::ATOM_ATOM, 32),
+            self.request(x::ATOM_WM_HINTS, x::ATOM_WM_HINTS, 9),
+            self.request(x::ATOM_WM_NORMAL_HINTS, x::ATOM_WM_SIZE_HINTS, 18),
+            self.request(x::ATOM_WM_CLASS, x::ATOM_ANY, 1024),
+            self.request(atoms.net_wm_name, x::ATOM_ANY, 1024),
+            self.request(x::ATOM_WM_NAME, x::ATOM_ANY, 1024),
+            self.request(atoms.wm_window_role, x::ATOM_ANY, 1024),
+            self.request(atoms.net_wm_pid, x::ATOM_CARDINAL, 1),
+            self.request(atoms.net_wm_strut_partial, x::ATOM_CARDINAL, 12),
+            self.request(atoms.net_wm_strut, x::ATOM_CARDINAL, 12),
+        ];
+
+        /* wait for every reply, so none is left behind on an error */
+        let replies: Result<Vec<_>, Error> = cookies.map(|cookie| self.conn.wait_for_reply(cookie))
+            .into_iter()
+            .collect();
+
+        let selected = self.conn.check_request(select).map_err(Error::from);
+
+        let replies = match selected.and(replies) {
+            Ok(replies) => replies,
+            /* the window was destroyed before we got to it */
+            Err(e) if e.bad_window() => return Ok(false),
+            Err(e) => return Err(e),
+        };
+
+        let [kind, protocols, hints, size_hints, class, net_name, name, role, pid, strut_partial, strut] = &replies[..] else {
+            unreachable!("one reply per request");
+        };
+
+        self.set_type(kind);
+        self.set_protocols(protocols);
+        self.set_hints(hints);
+        self.set_size_hints(size_hints);
+        self.set_class(class);
+        self.set_title(text(net_name).or_else(|| text(name)));
+        self.role = text(role);
+        self.set_pid(pid);
+        self.set_strut(&[strut_partial, strut]);
+
+        Ok(true)
+    }
+
+    /// Read _NET_WM_WINDOW_TYPE. Returns whether the type changed
+    pub(crate) fn load_type(&mut self) -> Result<bool, Error> {
+        let cookie = self.request(self.conn.atoms().net_wm_window_type, x::ATOM_ATOM, 32);
+        let reply = self.conn.wait_for_reply(cookie)?;
+
+        Ok(self.set_type(&reply))
+    }
+
+    fn set_type(&mut self, reply: &x::GetPropertyReply) -> bool {
+        let atoms = self.conn.atoms();
+
+        let types = [
+            (atoms.net_wm_window_type_normal, WindowType::Normal),
+            (atoms.net_wm_window_type_dialog, WindowType::Dialog),
+            (atoms.net_wm_window_type_dock, WindowType::Dock),
+            (atoms.net_wm_window_type_splash, WindowType::Splash),
+            (atoms.net_wm_window_type_utility, WindowType::Utility),
+            (atoms.net_wm_window_type_toolbar, WindowType::Toolbar),
+            (atoms.net_wm_window_type_notification, WindowType::Notification),
+            (atoms.net_wm_window_type_desktop, WindowType::Desktop),
+        ];
+
+        /* the first type we know of wins, the rest are fallbacks */
+        let kind = values::<x::Atom>(reply).iter()
+            .find_map(|atom| types.iter().find(|(a, _)| a == atom))
+            .map(|(_, kind)| *kind)
+            .unwrap_or(WindowType::Normal);
+
+        let changed = kind != self.kind;
+        self.kind = kind;
+
+        changed
+    }
+
+    /// Read WM_PROTOCOLS
+    pub(crate) fn load_protocols(&mut self) -> Result<(), Error> {
+        let cookie = self.request(self.conn.atoms().wm_protocols, x::ATOM_ATOM, 32);
+        let reply = self.conn.wait_for_reply(cookie)?;
+
+        self.set_protocols(&reply);
+
+        Ok(())
+    }
+
+    fn set_protocols(&mut self, reply: &x::GetPropertyReply) {
+        self.protocols = values::<x::Atom>(reply).to_vec();
+    }
+
+    /// Send a WM_PROTOCOLS client message
+    fn send_protocol(&self, protocol: x::Atom, time: x::Timestamp) -> Result<(), Error> {
+        let event = x::ClientMessageEvent::new(
+            self.window,
+            self.conn.atoms().wm_protocols,
+            x::ClientMessageData::Data32([protocol.resource_id(), time, 0, 0, 0]),
+        );
+
+        let cookie = self.conn.send_request_checked(&x::SendEvent {
+            propagate: false,
+            destination: x::SendEventDest::Window(self.window),
+            event_mask: x::EventMask::NO_EVENT,
+            event: &event,
+        });
+
+        self.conn.check_request(cookie)?;
+
+        Ok(())
+    }
+
+    /// Ask the client to close the window through WM_DELETE_WINDOW, at the
+    /// server time of the request. Returns false, doing nothing, if the
+    /// client does not support it
+    pub fn close(&self, time: x::Timestamp) -> Result<bool, Error> {
+        let delete = self.conn.atoms().wm_delete_window;
+
+        if !self.supports(delete) {
+            return Ok(false);
+        }
+
+        self.send_protocol(delete, time)?;
+
+        Ok(true)
+    }
+
+    /// Disconnect the client owning the window
+    pub fn kill(&self) -> Result<(), Error> {
+        let cookie = self.conn.send_request_checked(&x::KillClient {
+            resource: self.window.resource_id(),
+        });
+
+        self.conn.check_request(cookie)?;
+
+        Ok(())
+    }
+
+    /// Read a text property of any type
+    fn read_text(&self, property: x::Atom) -> Result<Option<String>, Error> {
+        let cookie = self.request(property, x::ATOM_ANY, 1024);
+        let reply = self.conn.wait_for_reply(cookie)?;
+
+        Ok(text(&reply))
+    }
+
+    /// Read WM_CLASS
+    pub(crate) fn load_class(&mut self) -> Result<(), Error> {
+        let cookie = self.request(x::ATOM_WM_CLASS, x::ATOM_ANY, 1024);
+        let reply = self.conn.wait_for_reply(cookie)?;
+
+        self.set_class(&reply);
+
+        Ok(())
+    }
+
+    fn set_class(&mut self, reply: &x::GetPropertyReply) {
+        let text = text(reply).unwrap_or_default();
+        let mut parts = text.split('\0').map(str::to_owned);
+
+        self.instance = parts.next().filter(|s| !s.is_empty());
+        self.class = parts.next().filter(|s| !s.is_empty());
+    }
+
+    /// Read _NET_WM_NAME, falling back to WM_NAME. Returns whether the
+    /// title changed
+    pub(crate) fn load_title(&mut self) -> Result<bool, Error> {
+        let title = match self.read_text(self.conn.atoms().net_wm_name)? {
+            Some(title) => Some(title),
+            None => self.read_text(x::ATOM_WM_NAME)?,
+        };
+
+        Ok(self.set_title(title))
+    }
+
+    fn set_title(&mut self, title: Option<String>) -> bool {
+        let changed = title != self.title;
+        self.title = title;
+
+        changed
+    }
+
+    /// Read WM_WINDOW_ROLE
+    pub(crate) fn load_role(&mut self) -> Result<(), Error> {
+        self.role = self.read_text(self.conn.atoms().wm_window_role)?;
+
+        Ok(())
+    }
+
+    /// Read _NET_WM_PID
+    pub(crate) fn load_pid(&mut self) -> Result<(), Error> {
+        let cookie = self.request(self.conn.atoms().net_wm_pid, x::ATOM_CARDINAL, 1);
+        let reply = self.conn.wait_for_reply(cookie)?;
+
+        self.set_pid(&reply);
+
+        Ok(())
+    }
+
+    fn set_pid(&mut self, reply: &x::GetPropertyReply) {
+        self.pid = values::<u32>(reply).first().copied();
+    }
+
+    /// Read WM_TRANSIENT_FOR
+    pub(crate) fn load_transient_for(&self) -> Result<Option<x::Window>, Error> {
+        let cookie = self.request(x::ATOM_WM_TRANSIENT_FOR, x::ATOM_WINDOW, 1);
+        let reply = self.conn.wait_for_reply(cookie)?;
+
+        Ok(values::<x::Window>(&reply).first().copied())
+    }
+
+    /// Read the input flag of WM_HINTS
+    pub(crate) fn load_hints(&mut self) -> Result<(), Error> {
+        let cookie = self.request(x::ATOM_WM_HINTS, x::ATOM_WM_HINTS, 9);
+        let reply = self.conn.wait_for_reply(cookie)?;
+
+        self.set_hints(&reply);
+
+        Ok(())
+    }
+
+    fn set_hints(&mut self, reply: &x::GetPropertyReply) {
+        /* InputHint is the first flag, the input field follows the flags */
+        self.input = match values::<u32>(reply) {
+            [flags, input, ..] if flags & 1 != 0 => *input != 0,
+            _ => true,
+        };
+    }
+
+    /// Read WM_NORMAL_HINTS. Returns whether the hints changed
+    pub(crate) fn load_size_hints(&mut self) -> Result<bool, Error> {
+        let cookie = self.request(x::ATOM_WM_NORMAL_HINTS, x::ATOM_WM_SIZE_HINTS, 18);
+        let reply = self.conn.wait_for_reply(cookie)?;
+
+        Ok(self.set_size_hints(&reply))
+    }
+
+    fn set_size_hints(&mut self, reply: &x::GetPropertyReply) -> bool {
+        let hints = SizeHints::new(values::<u32>(reply));
+
+        let changed = hints != self.hints;
+        self.hints = hints;
+
+        changed
+    }
+
+    /// Read _NET_WM_STRUT_PARTIAL, falling back to _NET_WM_STRUT. Only docks
+    /// may reserve space. Returns whether the strut changed
+    pub(crate) fn load_strut(&mut self) -> Result<bool, Error> {
+        let atoms = self.conn.atoms();
+
+        let partial = self.request(atoms.net_wm_strut_partial, x::ATOM_CARDINAL, 12);
+        let strut = self.request(atoms.net_wm_strut, x::ATOM_CARDINAL, 12);
+
+        let partial = self.conn.wait_for_reply(partial)?;
+        let strut = self.conn.wait_for_reply(strut)?;
+
+        Ok(self.set_strut(&[&partial, &strut]))
+    }
+
+    /// Take the first strut of replies that decodes
+    fn set_strut(&mut self, replies: &[&x::GetPropertyReply]) -> bool {
+        let strut = match self.kind {
+            WindowType::Dock => replies.iter().find_map(|reply| Strut::new(values::<u32>(reply))),
+            _ => None,
+        };
+
+        let changed = strut != self.strut;
+        self.strut = strut;
+
+        changed
+    }
+
+    /// States the client set in _NET_WM_STATE before mapping the window
+    pub(crate) fn requested_states(&self) -> Result<Vec<x::Atom>, Error> {
+        let cookie = self.conn.send_request(&x::GetProperty {
+            delete: false,
+            window: self.window,
+            property: self.conn.atoms().net_wm_state,
+            r#type: x::ATOM_ATOM,
+            long_offset: 0,
+            long_length: 32,
+        });
+
+        let reply = self.conn.wait_for_reply(cookie)?;
+
+        Ok(values::<x::Atom>(&reply).to_vec())
+    }
+
+    /// Publish the states we track in _NET_WM_STATE
+    fn update_state(&self) -> Result<(), Error> {
+        let atoms = self.conn.atoms();
+        let owned = [atoms.net_wm_state_fullscreen, atoms.net_wm_state_hidden];
+
+        let cookie = self.conn.send_request(&x::GetProperty {
+            delete: false,
+            window: self.window,
+            property: atoms.net_wm_state,
+            r#type: x::ATOM_ATOM,
+            long_offset: 0,
+            long_length: 32,
+        });
+
+        let reply = self.conn.wait_for_reply(cookie)?;
+
+        /* states we do not manage, e.g. set by the client before mapping,
+         * are kept as they are */
+        let mut states: Vec<x::Atom> = values::<x::Atom>(&reply).iter()
+            .filter(|atom| !owned.contains(atom))
+            .copied()
+            .collect();
+
+        if self.fullscreen {
+            states.push(atoms.net_wm_state_fullscreen);
+        }
+
+        if self.minimized {
+            states.push(atoms.net_wm_state_hidden);
+        }
+
+        let cookie = self.conn.send_request_checked(&x::ChangeProperty {
+            mode: x::PropMode::Replace,
+            window: self.window,
+            property: atoms.net_wm_state,
+            r#type: x::ATOM_ATOM,
+            data: &states,
+        });
+
+        self.conn.check_request(cookie)?;
+
+        Ok(())
+    }
+
+    /// Give the window the input focus the way its focus model asks for, at
+    /// the server time of the event that asked for it. Windows that take no
+    /// input are left alone
+    pub fn focus(&self, time: x::Timestamp) -> Result<(), Error> {
+        let model = self.focus_model();
+
+        if matches!(model, FocusModel::Passive | FocusModel::LocallyActive) {
+            let cookie = self.conn.send_request_checked(&x::SetInputFocus {
+                revert_to: x::InputFocus::PointerRoot,
+                focus: self.window,
+                time,
+            });
+
+            self.conn.check_request(cookie)?;
+        }
+
+        if matches!(model, FocusModel::LocallyActive | FocusModel::GloballyActive) {
+            self.send_protocol(self.conn.atoms().wm_take_focus, time)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Elements of a property, or none if it was stored in another format than
+/// the one asked for
+fn values<P: x::PropEl>(reply: &x::GetPropertyReply) -> &[P] {
+    if reply.format() == P::FORMAT {
+        reply.value::<P>()
+    } else {
+        &[]
+    }
+}
+
+/// Text of a property of any type. STRING is Latin-1, anything else is
+/// taken as UTF-8
+fn text(reply: &x::GetPropertyReply) -> Option<String> {
+    if reply.format() != 8 || reply.r#type() == x::ATOM_NONE {
+        return None;
+    }
+
+    let bytes = reply.value::<u8>();
+
+    let text = match reply.r#type() {
+        x::ATOM_STRING => bytes.iter().map(|b| *b as char).collect(),
+        _ => String::from_utf8_lossy(bytes).into_owned(),
+    };
+
+    Some(text)
 }
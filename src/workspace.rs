@@ -0,0 +1,172 @@
+use crate::arena::{Arena, Key};
+use crate::container::ContainerId;
+use crate::monitor::MonitorId;
+use crate::rect::Rect;
+
+/// Handle to a workspace. Ids of removed workspaces never refer to a later one
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct WorkspaceId(Key);
+
+/// A named layout directly under the container root
+pub struct Workspace {
+    name: String,
+    layout: ContainerId,
+    visible: bool,
+    /* the monitor the workspace is shown on */
+    monitor: Option<MonitorId>,
+    /* name of the monitor the workspace belongs to. it returns there
+     * whenever that monitor is connected */
+    home: Option<String>,
+    /* size of the monitor, for windows that cover all of it */
+    screen: Option<Rect>,
+}
+
+impl Workspace {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The layout holding every container on this workspace
+    pub fn layout(&self) -> ContainerId {
+        self.layout
+    }
+
+    pub fn visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Monitor the workspace is shown on, or would be if it were visible
+    pub fn monitor(&self) -> Option<MonitorId> {
+        self.monitor
+    }
+
+    /// Name of the monitor the workspace returns to once connected
+    pub fn home(&self) -> Option<&str> {
+        self.home.as_deref()
+    }
+
+    /// Size of the monitor the workspace is on
+    pub fn screen(&self) -> Option<Rect> {
+        self.screen
+    }
+}
+
+/// Every workspace, in the order they were created
+pub struct Workspaces {
+    workspaces: Arena<Workspace>,
+    /* the workspace holding the focus */
+    current: Option<WorkspaceId>,
+}
+
+impl Default for Workspaces {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Workspaces {
+    pub fn new() -> Self {
+        Workspaces {
+            workspaces: Arena::new(),
+            current: None,
+        }
+    }
+
+    pub fn get(&self, id: WorkspaceId) -> Option<&Workspace> {
+        self.workspaces.get(id.0)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (WorkspaceId, &Workspace)> {
+        self.workspaces.iter().map(|(k, ws)| (WorkspaceId(k), ws))
+    }
+
+    pub fn len(&self) -> usize {
+        self.workspaces.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.workspaces.is_empty()
+    }
+
+    pub fn current(&self) -> Option<WorkspaceId> {
+        self.current
+    }
+
+    /// First workspace called name
+    pub fn find(&self, name: &str) -> Option<WorkspaceId> {
+        self.iter()
+            .find(|(_, ws)| ws.name == name)
+            .map(|(id, _)| id)
+    }
+
+    /// Workspace whose layout is the given container
+    pub fn by_layout(&self, layout: ContainerId) -> Option<WorkspaceId> {
+        self.iter()
+            .find(|(_, ws)| ws.layout == layout)
+            .map(|(id, _)| id)
+    }
+
+    pub(crate) fn insert(&mut self, name: &str, layout: ContainerId) -> WorkspaceId {
+        let key = self.workspaces.insert(Workspace {
+            name: name.to_owned(),
+            layout,
+            visible: false,
+            monitor: None,
+            home: None,
+            screen: None,
+        });
+
+        WorkspaceId(key)
+    }
+
+    /// Remove a workspace, e.g. once its layout is gone
+    pub(crate) fn remove(&mut self, id: WorkspaceId) -> Option<Workspace> {
+        let workspace = self.workspaces.remove(id.0)?;
+
+        if self.current == Some(id) {
+            self.current = None;
+        }
+
+        Some(workspace)
+    }
+
+    /// Rename a workspace. Returns false if id does not exist
+    pub(crate) fn rename(&mut self, id: WorkspaceId, name: &str) -> bool {
+        match self.get_mut(id) {
+            Some(ws) => {
+                ws.name = name.to_owned();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub(crate) fn set_visible(&mut self, id: WorkspaceId, visible: bool) {
+        if let Some(ws) = self.get_mut(id) {
+            ws.visible = visible;
+        }
+    }
+
+    pub(crate) fn set_monitor(&mut self, id: WorkspaceId, monitor: Option<MonitorId>, screen: Option<Rect>) {
+        if let Some(ws) = self.get_mut(id) {
+            ws.monitor = monitor;
+            ws.screen = screen;
+        }
+    }
+
+    pub(crate) fn set_home(&mut self, id: WorkspaceId, home: Option<String>) {
+        if let Some(ws) = self.get_mut(id) {
+            ws.home = home;
+        }
+    }
+
+    pub(crate) fn set_current(&mut self, id: Option<WorkspaceId>) {
+        self.current = id;
+    }
+}
+
+impl Workspaces {
+    fn get_mut(&mut self, id: WorkspaceId) -> Option<&mut Workspace> {
+        self.workspaces.get_mut(id.0)
+    }
+}